@@ -0,0 +1,76 @@
+//! abbreviations, with the syntax of PHP Markdown Extra:
+//! after `*[HTML]: Hyper Text Markup Language` on its own line,
+//! each `HTML` of the text is explained by a tooltip.
+
+use core::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+use crate::embed::Embed;
+use crate::prepare::Pass;
+
+/// the abbreviation and its expansion, in `*[HTML]: Hyper Text Markup Language`
+fn parse_definition(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("*[")?;
+    let (abbreviation, rest) = rest.split_once("]:")?;
+    let abbreviation = abbreviation.trim();
+    (!abbreviation.is_empty()).then_some((abbreviation, rest.trim()))
+}
+
+pub(crate) fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// the longest abbreviation at the start of `text` that is a whole word
+fn find<'d>(text: &str, definitions: &'d [(String, String)]) -> Option<&'d (String, String)> {
+    definitions
+        .iter()
+        .filter(|(abbreviation, _)| {
+            text.strip_prefix(abbreviation.as_str())
+                .is_some_and(|rest| !rest.starts_with(is_word_char))
+        })
+        .max_by_key(|(abbreviation, _)| abbreviation.len())
+}
+
+/// remove the definitions, and replace the abbreviations in the text by embeds
+pub(crate) fn abbreviations(pass: &mut Pass) {
+    let source = pass.source;
+    let events = pass.events();
+    let code: Vec<Range<usize>> = events
+        .iter()
+        .filter(|(e, _)| matches!(e, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_)))))
+        .map(|(_, range)| range.clone())
+        .collect();
+
+    let mut definitions: Vec<(String, String)> = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        let range = start..start + line.len();
+        start = range.end;
+        if code.iter().any(|c| c.contains(&range.start)) {
+            continue;
+        }
+        if let Some((abbreviation, expansion)) = parse_definition(line) {
+            definitions.retain(|(a, _)| a != abbreviation);
+            definitions.push((abbreviation.to_string(), expansion.to_string()));
+            pass.replace(range, "");
+        }
+    }
+    if definitions.is_empty() {
+        return;
+    }
+
+    let runs = pass.text_runs(&events, |_| false);
+    let found = pass.scan(&runs, |before, text| {
+        let (abbreviation, expansion) = find(text, &definitions).filter(|_| !before.ends_with(is_word_char))?;
+        Some((abbreviation.len(), expansion.clone()))
+    });
+    for (range, title) in found {
+        let embed = Embed::Abbreviation {
+            text: source[range.clone()].to_string(),
+            title,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, embed);
+    }
+}
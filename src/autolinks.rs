@@ -0,0 +1,147 @@
+//! the extended autolinks of GitHub Flavored Markdown:
+//! `https://...`, `http://...` and `www....` are links without `<...>`.
+//!
+//! They are written in the source as markdown links,
+//! so that they are rendered by `render_links` like the others.
+
+use pulldown_cmark::Tag;
+
+use crate::mentions::escape;
+use crate::prepare::Pass;
+use crate::source::Piece;
+
+/// whether an autolink can start after `before`
+fn can_start_after(before: &str) -> bool {
+    before
+        .chars()
+        .next_back()
+        .map_or(true, |c| c.is_whitespace() || "*_~(".contains(c))
+}
+
+/// a domain with at least one period, and no underscore in its last two segments
+fn valid_domain(domain: &str) -> bool {
+    let segments: Vec<&str> = domain.split('.').collect();
+    segments.len() >= 2
+        && segments.iter().all(|s| {
+            !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        })
+        && segments[segments.len() - 2..].iter().all(|s| !s.contains('_'))
+}
+
+/// the length of the link at the start of `text`, without its trailing punctuation
+fn link_len(text: &str) -> usize {
+    let mut end = text.find(|c: char| c.is_whitespace() || c == '<').unwrap_or(text.len());
+    loop {
+        let link = &text[..end];
+        let Some(last) = link.chars().next_back() else {
+            return 0;
+        };
+        if "?!.,:*_~'\"".contains(last) {
+            end -= 1;
+        } else if last == ')' && link.matches(')').count() > link.matches('(').count() {
+            end -= 1;
+        } else if last == ';' {
+            // an entity reference like `&amp;` is not part of the link
+            match link.rfind('&') {
+                Some(amp) if link[amp + 1..end - 1].chars().all(|c| c.is_ascii_alphanumeric()) => end = amp,
+                _ => return end,
+            }
+        } else {
+            return end;
+        }
+    }
+}
+
+/// the link at the start of `text`: its length, and whether it starts with `www.`
+fn parse(text: &str) -> Option<(usize, bool)> {
+    let (rest, www) = if let Some(rest) = text.strip_prefix("https://").or_else(|| text.strip_prefix("http://")) {
+        (rest, false)
+    } else if text.starts_with("www.") {
+        (text, true)
+    } else {
+        return None;
+    };
+    let len = link_len(text);
+    let prefix_len = text.len() - rest.len();
+    if len <= prefix_len {
+        return None;
+    }
+    let domain = &text[prefix_len..len];
+    let domain = &domain[..domain.find(['/', '?', '#', ':']).unwrap_or(domain.len())];
+    valid_domain(domain).then_some((len, www))
+}
+
+/// replace the urls of the text by links
+pub(crate) fn autolinks(pass: &mut Pass) {
+    let source = pass.source;
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..)));
+    let found = pass.scan(&runs, |before, text| parse(text).filter(|_| can_start_after(before)));
+    for (range, www) in found {
+        let pieces = match www {
+            false => vec![
+                Piece::Text("<".to_string()),
+                Piece::Copy(range.clone()),
+                Piece::Text(">".to_string()),
+            ],
+            true => vec![
+                Piece::Text("[".to_string()),
+                Piece::Text(escape(&source[range.clone()])),
+                Piece::Text("](<http://".to_string()),
+                Piece::Copy(range.clone()),
+                Piece::Text(">)".to_string()),
+            ],
+        };
+        pass.replace_with(range, pieces);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn link(text: &str) -> &str {
+        &text[..link_len(text)]
+    }
+
+    #[wasm_bindgen_test]
+    fn trailing_punctuation_is_not_part_of_the_link() {
+        assert_eq!(link("https://example.com/a."), "https://example.com/a");
+        assert_eq!(link("https://example.com/?q=1!\" and"), "https://example.com/?q=1");
+    }
+
+    #[wasm_bindgen_test]
+    fn only_unbalanced_parentheses_are_removed() {
+        assert_eq!(
+            link("https://en.wikipedia.org/wiki/Rust_(language))"),
+            "https://en.wikipedia.org/wiki/Rust_(language)"
+        );
+        assert_eq!(link("https://example.com/a)"), "https://example.com/a");
+    }
+
+    #[wasm_bindgen_test]
+    fn entity_references_are_not_part_of_the_link() {
+        assert_eq!(link("www.example.com&amp; x"), "www.example.com");
+        assert_eq!(link("www.example.com/a&b"), "www.example.com/a&b");
+    }
+
+    #[wasm_bindgen_test]
+    fn links_need_a_valid_domain() {
+        assert_eq!(parse("https://github.com/wooorm/markdown"), Some((34, false)));
+        assert_eq!(parse("www.example.com, next"), Some((15, true)));
+        assert_eq!(parse("https://localhost"), None);
+        assert_eq!(parse("http://a_b.example_x.com"), None);
+        assert_eq!(parse("https://"), None);
+        assert_eq!(parse("ftp://example.com"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn links_start_after_spaces_and_delimiters() {
+        assert!(can_start_after(""));
+        assert!(can_start_after("see "));
+        assert!(can_start_after("*("));
+        assert!(!can_start_after("x"));
+    }
+}
@@ -0,0 +1,300 @@
+//! the works that can be cited, read from BibTeX or CSL-JSON.
+
+/// an author of a work
+#[derive(Clone, Debug, PartialEq)]
+pub struct Person {
+    pub family: String,
+    pub given: String,
+}
+
+impl Person {
+    /// a name from BibTeX: `Knuth, Donald E.` or `Donald E. Knuth`
+    fn parse(name: &str) -> Self {
+        let name = clean(name);
+        match name.split_once(',') {
+            Some((family, given)) => Person {
+                family: family.trim().to_string(),
+                given: given.trim().to_string(),
+            },
+            None => match name.rsplit_once(' ') {
+                Some((given, family)) => Person {
+                    family: family.to_string(),
+                    given: given.to_string(),
+                },
+                None => Person {
+                    family: name,
+                    given: String::new(),
+                },
+            },
+        }
+    }
+
+    /// `Knuth, Donald E.`
+    pub fn full_name(&self) -> String {
+        match self.given.as_str() {
+            "" => self.family.clone(),
+            given => format!("{}, {given}", self.family),
+        }
+    }
+}
+
+/// a work that can be cited
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BibEntry {
+    /// the key used to cite it, like `knuth1984`
+    pub key: String,
+    pub authors: Vec<Person>,
+    pub title: String,
+    pub year: Option<String>,
+    /// the journal, the book or the publisher
+    pub container: Option<String>,
+    pub url: Option<String>,
+}
+
+impl BibEntry {
+    /// `Knuth`, `Knuth and Lamport` or `Knuth et al.`
+    pub fn short_authors(&self) -> String {
+        match self.authors.as_slice() {
+            [] => self.title.clone(),
+            [a] => a.family.clone(),
+            [a, b] => format!("{} and {}", a.family, b.family),
+            [a, ..] => format!("{} et al.", a.family),
+        }
+    }
+}
+
+/// the works that can be cited, as given to the `bibliography` of [`Props`](crate::Props).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bibliography {
+    pub entries: Vec<BibEntry>,
+}
+
+/// `text` without braces, and with its whitespace collapsed
+fn clean(text: &str) -> String {
+    text.replace(['{', '}'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the content between the delimiter at the start of `s` and the one that closes it,
+/// and what is after
+fn balanced(s: &str) -> Result<(&str, &str), String> {
+    let (open, close) = match s.chars().next() {
+        Some('{') => ('{', '}'),
+        Some('(') => ('(', ')'),
+        _ => return Err("expected `{` or `(`".to_string()),
+    };
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Ok((&s[1..i], &s[i + 1..]));
+            }
+        }
+    }
+    Err(format!("a `{open}` is not closed"))
+}
+
+/// the fields of a BibTeX entry, after its key
+fn parse_fields(mut s: &str) -> Result<Vec<(String, String)>, String> {
+    let mut fields = Vec::new();
+    loop {
+        s = s.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if s.is_empty() {
+            return Ok(fields);
+        }
+        let (name, rest) = s.split_once('=').ok_or("a field has no `=`")?;
+        let rest = rest.trim_start();
+        let (value, rest) = match rest.chars().next() {
+            Some('{') => balanced(rest)?,
+            Some('"') => {
+                let end = rest[1..].find('"').ok_or("a `\"` is not closed")?;
+                (&rest[1..end + 1], &rest[end + 2..])
+            }
+            _ => {
+                let end = rest.find(',').unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        fields.push((name.trim().to_lowercase(), value.to_string()));
+        s = rest;
+    }
+}
+
+impl Bibliography {
+    pub fn new(entries: Vec<BibEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&BibEntry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    /// read the entries of a BibTeX file.
+    /// The `@string` abbreviations are not expanded.
+    pub fn from_bibtex(source: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        let mut rest = source;
+        while let Some(at) = rest.find('@') {
+            rest = &rest[at + 1..];
+            let open = rest.find(['{', '(']).ok_or("an entry has no `{`")?;
+            let kind = rest[..open].trim().to_lowercase();
+            let (body, after) = balanced(&rest[open..])?;
+            rest = after;
+            if matches!(kind.as_str(), "comment" | "string" | "preamble") {
+                continue;
+            }
+
+            let (key, fields) = body.split_once(',').unwrap_or((body, ""));
+            let mut entry = BibEntry {
+                key: key.trim().to_string(),
+                ..BibEntry::default()
+            };
+            let mut doi = None;
+            for (name, value) in parse_fields(fields).map_err(|e| format!("in `{}`: {e}", entry.key))? {
+                match name.as_str() {
+                    "author" => entry.authors = clean(&value).split(" and ").map(Person::parse).collect(),
+                    "title" => entry.title = clean(&value),
+                    "year" => entry.year = Some(clean(&value)),
+                    "date" if entry.year.is_none() => entry.year = Some(clean(&value).chars().take(4).collect()),
+                    "journal" | "booktitle" => entry.container = Some(clean(&value)),
+                    "publisher" if entry.container.is_none() => entry.container = Some(clean(&value)),
+                    "url" => entry.url = Some(clean(&value)),
+                    "doi" => doi = Some(clean(&value)),
+                    _ => {}
+                }
+            }
+            if entry.url.is_none() {
+                entry.url = doi.map(|doi| format!("https://doi.org/{doi}"));
+            }
+            entries.push(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// read the entries of a CSL-JSON file
+    #[cfg(feature = "csl-json")]
+    pub fn from_csl_json(source: &str) -> Result<Self, String> {
+        use serde_json::Value;
+
+        let items: Vec<Value> = serde_json::from_str(source).map_err(|e| e.to_string())?;
+        let text = |item: &Value, field: &str| item.get(field).and_then(Value::as_str).map(str::to_string);
+        let entries = items
+            .iter()
+            .map(|item| {
+                let authors = item
+                    .get("author")
+                    .and_then(Value::as_array)
+                    .map(|authors| {
+                        authors
+                            .iter()
+                            .map(|a| Person {
+                                family: text(a, "family").or_else(|| text(a, "literal")).unwrap_or_default(),
+                                given: text(a, "given").unwrap_or_default(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let year = item
+                    .pointer("/issued/date-parts/0/0")
+                    .map(|y| y.to_string().trim_matches('"').to_string());
+                BibEntry {
+                    key: text(item, "id").unwrap_or_default(),
+                    authors,
+                    title: text(item, "title").unwrap_or_default(),
+                    year,
+                    container: text(item, "container-title").or_else(|| text(item, "publisher")),
+                    url: text(item, "URL").or_else(|| text(item, "DOI").map(|doi| format!("https://doi.org/{doi}"))),
+                }
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    const BIBTEX: &str = r#"
+@article{knuth1984,
+  author = {Knuth, Donald E. and
+            Lamport, Leslie},
+  title = {Literate {P}rogramming},
+  journal = "The Computer Journal",
+  year = 1984,
+  doi = {10.1093/comjnl/27.2.97},
+}
+
+@comment{not an entry}
+
+@book{lamport1994, author = {Leslie Lamport}, title = {LaTeX}, publisher = {Addison-Wesley}, date = {1994-01-01}}
+"#;
+
+    fn person(family: &str, given: &str) -> Person {
+        Person {
+            family: family.to_string(),
+            given: given.to_string(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn bibtex_entries() {
+        let bibliography = Bibliography::from_bibtex(BIBTEX).unwrap();
+        assert_eq!(bibliography.entries.len(), 2);
+
+        let knuth = bibliography.get("knuth1984").unwrap();
+        assert_eq!(knuth.authors, vec![person("Knuth", "Donald E."), person("Lamport", "Leslie")]);
+        assert_eq!(knuth.title, "Literate Programming");
+        assert_eq!(knuth.year.as_deref(), Some("1984"));
+        assert_eq!(knuth.container.as_deref(), Some("The Computer Journal"));
+        assert_eq!(knuth.url.as_deref(), Some("https://doi.org/10.1093/comjnl/27.2.97"));
+
+        let lamport = bibliography.get("lamport1994").unwrap();
+        assert_eq!(lamport.authors, vec![person("Lamport", "Leslie")]);
+        assert_eq!(lamport.year.as_deref(), Some("1994"));
+        assert_eq!(lamport.container.as_deref(), Some("Addison-Wesley"));
+    }
+
+    #[wasm_bindgen_test]
+    fn unclosed_bibtex_entry() {
+        assert!(Bibliography::from_bibtex("@article{x, title = {unclosed}").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn author_names() {
+        let entry = |authors: Vec<Person>| BibEntry {
+            authors,
+            ..BibEntry::default()
+        };
+        let knuth = person("Knuth", "Donald E.");
+        let lamport = person("Lamport", "Leslie");
+        assert_eq!(knuth.full_name(), "Knuth, Donald E.");
+        assert_eq!(entry(vec![knuth.clone()]).short_authors(), "Knuth");
+        assert_eq!(entry(vec![knuth.clone(), lamport.clone()]).short_authors(), "Knuth and Lamport");
+        assert_eq!(entry(vec![knuth.clone(), lamport, knuth]).short_authors(), "Knuth et al.");
+    }
+
+    #[cfg(feature = "csl-json")]
+    #[wasm_bindgen_test]
+    fn csl_json_entries() {
+        let source = r#"[{
+            "id": "knuth1984",
+            "author": [{"family": "Knuth", "given": "Donald E."}],
+            "title": "Literate Programming",
+            "issued": {"date-parts": [[1984]]},
+            "container-title": "The Computer Journal"
+        }]"#;
+        let bibliography = Bibliography::from_csl_json(source).unwrap();
+        let knuth = bibliography.get("knuth1984").unwrap();
+        assert_eq!(knuth.authors, vec![person("Knuth", "Donald E.")]);
+        assert_eq!(knuth.year.as_deref(), Some("1984"));
+        assert_eq!(knuth.container.as_deref(), Some("The Computer Journal"));
+    }
+}
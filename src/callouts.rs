@@ -0,0 +1,192 @@
+//! callouts: block quotes starting with `[!NOTE]`, `[!WARNING]`...,
+//! like the alerts of GitHub. A title can follow the marker: `> [!TIP] Faster builds`.
+
+use core::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use yew::prelude::{AttrValue, Html};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::prepare::Pass;
+use crate::source::Piece;
+
+/// a kind of callout, like `note` or `warning`.
+/// The kinds of GitHub are always available, see [`CalloutKind::builtin`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalloutKind {
+    /// the word between `[!` and `]`, matched without case
+    pub name: AttrValue,
+    /// the title of the callouts that do not have one
+    pub title: AttrValue,
+    /// shown before the title
+    pub icon: AttrValue,
+}
+
+impl CalloutKind {
+    pub fn new(name: impl Into<AttrValue>, title: impl Into<AttrValue>, icon: impl Into<AttrValue>) -> Self {
+        Self {
+            name: name.into(),
+            title: title.into(),
+            icon: icon.into(),
+        }
+    }
+
+    /// `note`, `tip`, `important`, `warning` and `caution`
+    pub fn builtin() -> [CalloutKind; 5] {
+        [
+            CalloutKind::new("note", "Note", "ℹ️"),
+            CalloutKind::new("tip", "Tip", "💡"),
+            CalloutKind::new("important", "Important", "❗"),
+            CalloutKind::new("warning", "Warning", "⚠️"),
+            CalloutKind::new("caution", "Caution", "🛑"),
+        ]
+    }
+}
+
+/// a callout, as given to the `callout_renderer` of [`Props`](crate::Props).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Callout {
+    /// the name of its kind, in lowercase
+    pub kind: AttrValue,
+    pub title: AttrValue,
+    pub icon: AttrValue,
+    /// the content, without the line of the marker
+    pub content: Html,
+    /// the range of the block quote in the markdown source
+    pub position: Range<usize>,
+}
+
+/// the kind and the title after `> [!KIND] title`
+fn parse_marker(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start().strip_prefix('>')?.trim_start();
+    let rest = rest.strip_prefix("[!")?;
+    let end = rest.find(']')?;
+    Some((&rest[..end], rest[end + 1..].trim()))
+}
+
+/// a line of a block quote starting at `start`, without its `>`.
+/// The first `outer_len` bytes are the prefix of the containers around the block quote.
+fn unquote(start: usize, line: &str, outer_len: usize) -> [Piece; 2] {
+    let mut outer_len = outer_len.min(line.len());
+    if !line.is_char_boundary(outer_len) {
+        outer_len = 0;
+    }
+    let inner = &line[outer_len..];
+    let unquoted = match inner.trim_start_matches(' ').strip_prefix('>') {
+        Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+        // a lazy continuation line
+        None => inner,
+    };
+    let end = start + line.len();
+    [
+        Piece::Copy(start..start + outer_len),
+        Piece::Copy(end - unquoted.len()..end),
+    ]
+}
+
+/// replace the block quotes that are callouts by an embed around their content.
+/// The kind and original position of each callout is added to `found`.
+///
+/// A callout inside another one is left for the next pass.
+/// Returns whether a callout was found.
+pub(crate) fn callouts(
+    pass: &mut Pass,
+    kinds: &[CalloutKind],
+    found: &mut Vec<(Range<usize>, AttrValue)>,
+) -> bool {
+    let source = pass.source;
+    let mut replaced_until = 0;
+    for (event, range) in pass.events() {
+        if range.start < replaced_until {
+            continue;
+        }
+        let Event::Start(Tag::BlockQuote) = event else {
+            continue;
+        };
+        let first_line_end = source[range.clone()]
+            .find('\n')
+            .map_or(range.end, |i| range.start + i);
+        let Some((name, title)) = parse_marker(&source[range.start..first_line_end]) else {
+            continue;
+        };
+        // the kinds given by the user can replace the built-in ones
+        let builtin = CalloutKind::builtin();
+        let Some(kind) = kinds
+            .iter()
+            .rev()
+            .chain(&builtin)
+            .find(|k| k.name.eq_ignore_ascii_case(name))
+        else {
+            continue;
+        };
+
+        let position = pass.original_range(range.clone());
+        let kind_name = AttrValue::from(kind.name.to_lowercase());
+        found.push((position.clone(), kind_name.clone()));
+        let tag = pass.open_tag(Embed::Callout {
+            kind: kind_name,
+            title: match title {
+                "" => kind.title.clone(),
+                title => AttrValue::from(title.to_string()),
+            },
+            icon: kind.icon.clone(),
+            position,
+        });
+
+        let end = range.start + source[range.clone()].trim_end().len();
+        let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let outer_len = range.start - line_start;
+
+        let mut open = pass.block_lines(range.start, &[tag]);
+        // the next lines already have their prefix
+        open.truncate(open.rfind('\n').map_or(0, |i| i + 1));
+        let mut pieces = vec![Piece::Text(open)];
+        let mut start = (first_line_end + 1).min(end);
+        for line in source[start..end].split_inclusive('\n') {
+            pieces.extend(unquote(start, line, outer_len));
+            start += line.len();
+        }
+        pieces.push(Piece::Text(pass.block_lines(range.start, &[CLOSE_TAG])));
+        pass.replace_with(range.start..end, pieces);
+        replaced_until = range.end;
+    }
+    replaced_until > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn prepare(src: &'static str) -> Prepared {
+        Prepared::new(&yew::props!(Props { src }))
+    }
+
+    #[wasm_bindgen_test]
+    fn nested_callouts() {
+        let prepared = prepare("> [!NOTE]\n> Outer\n>\n> > [!TIP] Inner\n> > text\n");
+        assert_eq!(
+            prepared.source,
+            "\n\n<YewMdEmbed id=\"0\">\n\nOuter\n\n\n\n<YewMdEmbed id=\"1\">\n\ntext\n\n</YewMdEmbed>\n\n\n\n</YewMdEmbed>\n\n\n"
+        );
+        assert!(matches!(&prepared.embeds[0], Embed::Callout { kind, .. } if kind.as_str() == "note"));
+        assert!(matches!(&prepared.embeds[1], Embed::Callout { title, .. } if title.as_str() == "Inner"));
+
+        // the inner callout is found in the second pass, its position is still in the source
+        let starts: Vec<_> = prepared.callouts.iter().map(|(p, kind)| (p.start, kind.as_str())).collect();
+        assert_eq!(starts, [(0, "note"), (22, "tip")]);
+        assert_eq!(prepared.original_range(23..28), 12..17);
+        assert_eq!(prepared.original_range(53..57), 41..45);
+    }
+
+    #[wasm_bindgen_test]
+    fn unknown_kinds_stay_block_quotes() {
+        let prepared = prepare("> [!FOO]\n> text\n");
+        assert_eq!(prepared.source, "> [!FOO]\n> text\n");
+        assert!(prepared.callouts.is_empty());
+    }
+}
@@ -0,0 +1,197 @@
+//! Pandoc citations: `[@knuth1984, p. 33]`, `[see @knuth1984; @lamport1994]`,
+//! or `[-@knuth1984]` for the year alone, resolved against the `bibliography`.
+//! The works that are cited are listed at the end of the document.
+
+use pulldown_cmark::Tag;
+
+use crate::bibliography::{BibEntry, Bibliography};
+use crate::embed::Embed;
+use crate::prepare::Pass;
+use crate::xref::TargetKind;
+
+/// one of the works of a citation
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CitationItem {
+    /// `None` if the work is not in the bibliography
+    pub key: Option<String>,
+    /// like `see Knuth 1984, p. 33`
+    pub text: String,
+}
+
+struct Parsed<'s> {
+    prefix: &'s str,
+    key: &'s str,
+    suppress_author: bool,
+    locator: &'s str,
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(c)
+}
+
+/// whether `before` ends where a word can start
+fn word_start(before: &str) -> bool {
+    before.chars().next_back().map_or(true, char::is_whitespace)
+}
+
+/// `see @knuth1984, p. 33`.
+/// Like in Pandoc, the `@` is at the start of a word or after a `-`,
+/// so that `bob@example.com` is not a citation.
+fn parse_item(s: &str) -> Option<Parsed<'_>> {
+    let (at, suppress_author) = s.match_indices('@').find_map(|(at, _)| {
+        let before = &s[..at];
+        match before.strip_suffix('-') {
+            Some(before) if word_start(before) => Some((at, true)),
+            _ if word_start(before) => Some((at, false)),
+            _ => None,
+        }
+    })?;
+    let prefix = &s[..at - suppress_author as usize];
+    let rest = &s[at + 1..];
+    let key_len = rest.find(|c| !is_key_char(c)).unwrap_or(rest.len());
+    // the punctuation at the end is not part of the key
+    let key = rest[..key_len].trim_end_matches(|c: char| !c.is_alphanumeric());
+    if key.is_empty() {
+        return None;
+    }
+    Some(Parsed {
+        prefix: prefix.trim(),
+        key,
+        suppress_author,
+        locator: rest[key.len()..].trim().trim_start_matches(',').trim(),
+    })
+}
+
+/// the works between `[` and `]`, separated by `;`
+fn parse_citation(content: &str) -> Option<Vec<Parsed<'_>>> {
+    content.split(';').map(parse_item).collect()
+}
+
+fn item_text(parsed: &Parsed, entry: Option<&BibEntry>) -> String {
+    let mut text = String::new();
+    if !parsed.prefix.is_empty() {
+        text += parsed.prefix;
+        text.push(' ');
+    }
+    match entry {
+        Some(entry) => {
+            if !parsed.suppress_author {
+                text += &entry.short_authors();
+                text.push(' ');
+            }
+            text += entry.year.as_deref().unwrap_or("n.d.");
+        }
+        None => text += parsed.key,
+    }
+    if !parsed.locator.is_empty() {
+        text += ", ";
+        text += parsed.locator;
+    }
+    text
+}
+
+/// replace the citations by embeds, and list the works that are cited at the end
+pub(crate) fn citations(pass: &mut Pass, bibliography: &Bibliography) {
+    let mut cited: Vec<&BibEntry> = Vec::new();
+    let source = pass.source;
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..)));
+    let found = pass.scan(&runs, |_, text| {
+        let content = text.strip_prefix('[')?;
+        let content = &content[..content.find(']')?];
+        let parsed = parse_citation(content)?;
+        // `[@eq:...]`, `[@fig:...]` and `[@tbl:...]` are references to numbered elements
+        if parsed.iter().any(|p| TargetKind::from_label(p.key).is_some()) {
+            return None;
+        }
+        Some((content.len() + 2, parsed))
+    });
+    for (range, parsed) in found {
+        let mut items = Vec::new();
+        for parsed in parsed {
+            let entry = bibliography.get(parsed.key);
+            match entry {
+                Some(entry) if !cited.iter().any(|e| e.key == entry.key) => cited.push(entry),
+                Some(_) => {}
+                None => pass.diagnostic(range.clone(), format!("`{}` is not in the bibliography", parsed.key)),
+            }
+            items.push(CitationItem {
+                key: entry.map(|e| e.key.clone()),
+                text: item_text(&parsed, entry),
+            });
+        }
+        let embed = Embed::Citation {
+            items,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, embed);
+    }
+
+    if cited.is_empty() {
+        return;
+    }
+    cited.sort_by(|a, b| {
+        let family = |e: &BibEntry| e.authors.first().map(|a| a.family.to_lowercase());
+        (family(a), &a.year).cmp(&(family(b), &b.year))
+    });
+    let entries = cited.into_iter().cloned().collect();
+    let tag = pass.tag(Embed::Bibliography { entries });
+    pass.replace(source.len()..source.len(), format!("\n\n{tag}\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::bibliography::Person;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    #[wasm_bindgen_test]
+    fn prefixes_locators_and_suppressed_authors() {
+        let items = parse_citation("see @knuth1984, p. 33; -@lamport1994").unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].prefix, items[0].key, items[0].locator), ("see", "knuth1984", "p. 33"));
+        assert!(!items[0].suppress_author);
+        assert_eq!((items[1].prefix, items[1].key, items[1].locator), ("", "lamport1994", ""));
+        assert!(items[1].suppress_author);
+    }
+
+    #[wasm_bindgen_test]
+    fn an_at_inside_a_word_is_not_a_citation() {
+        assert!(parse_citation("contact bob@example.com").is_none());
+        assert!(parse_citation("see @knuth1984; bob@example.com").is_none());
+        assert!(parse_citation("no key").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn item_texts() {
+        let entry = BibEntry {
+            key: "knuth1984".to_string(),
+            authors: vec![Person {
+                family: "Knuth".to_string(),
+                given: "Donald E.".to_string(),
+            }],
+            year: Some("1984".to_string()),
+            ..BibEntry::default()
+        };
+        let items = parse_citation("see @knuth1984, p. 33; -@knuth1984").unwrap();
+        assert_eq!(item_text(&items[0], Some(&entry)), "see Knuth 1984, p. 33");
+        assert_eq!(item_text(&items[1], Some(&entry)), "1984");
+        assert_eq!(item_text(&items[0], None), "see knuth1984, p. 33");
+    }
+
+    #[wasm_bindgen_test]
+    fn references_are_not_citations() {
+        let bibliography = Rc::new(Bibliography::new(Vec::new()));
+        let src = "See [@fig:plot] and [@eq:euler].\n";
+        let prepared = Prepared::new(&yew::props!(Props { src, bibliography }));
+        // only the references report the unknown labels
+        assert_eq!(prepared.diagnostics.len(), 2);
+        assert!(prepared.diagnostics[0].message.contains("label"));
+        assert!(prepared.embeds.iter().all(|e| !matches!(e, Embed::Citation { .. })));
+    }
+}
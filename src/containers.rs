@@ -0,0 +1,239 @@
+//! blocks between fences of colons, like
+//!
+//! ```markdown
+//! :::details Title
+//! content
+//! :::
+//! ```
+//!
+//! The fences must start their line. The raw html `<details>` and `</details>`
+//! lines are also read as fences, so that the markdown between them is rendered.
+//!
+//! Tabs are `:::tab Title` containers inside a `:::tabs` container.
+//! A `:::spoiler Label` container is hidden until it is clicked.
+
+use core::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::prepare::Pass;
+
+enum Fence<'s> {
+    Open {
+        name: &'s str,
+        args: &'s str,
+        /// `<details>` instead of `:::details`
+        html: bool,
+    },
+    Close {
+        html: bool,
+    },
+}
+
+fn parse_fence(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim_end();
+    if let Some(rest) = line.strip_prefix(":::") {
+        let rest = rest.trim_start_matches(':').trim();
+        return Some(match rest.split_once(char::is_whitespace) {
+            _ if rest.is_empty() => Fence::Close { html: false },
+            Some((name, args)) => Fence::Open {
+                name,
+                args: args.trim(),
+                html: false,
+            },
+            None => Fence::Open {
+                name: rest,
+                args: "",
+                html: false,
+            },
+        });
+    }
+    if line == "</details>" {
+        return Some(Fence::Close { html: true });
+    }
+    let attributes = line.strip_prefix("<details")?.strip_suffix('>')?;
+    match attributes.trim() {
+        "" | "open" => Some(Fence::Open {
+            name: "details",
+            args: attributes.trim(),
+            html: true,
+        }),
+        _ => None,
+    }
+}
+
+/// the text of `<summary>...</summary>`
+fn parse_summary(line: &str) -> Option<&str> {
+    let inner = line.trim().strip_prefix("<summary>")?.strip_suffix("</summary>")?;
+    Some(inner.trim())
+}
+
+struct Opening<'s> {
+    name: &'s str,
+    args: &'s str,
+    html: bool,
+    /// the `<summary>` after `<details>`
+    summary: Option<&'s str>,
+    /// the lines of the fence
+    lines: Range<usize>,
+    /// the titles of the tabs of a `:::tabs` container, found so far
+    tabs: Vec<String>,
+}
+
+/// the embed for a container, or `None` if it is not known
+fn embed(pass: &Pass, opening: &Opening, parent: Option<&mut Opening>, range: Range<usize>) -> Option<Embed> {
+    match opening.name {
+        "tabs" => Some(Embed::Tabs {
+            titles: opening.tabs.clone(),
+        }),
+        "tab" => {
+            let parent = parent.filter(|p| p.name == "tabs")?;
+            parent.tabs.push(match opening.args {
+                "" => format!("Tab {}", parent.tabs.len() + 1),
+                title => title.to_string(),
+            });
+            Some(Embed::Tab {
+                index: parent.tabs.len() - 1,
+                position: pass.original_range(range),
+            })
+        }
+        "spoiler" => Some(Embed::SpoilerBlock {
+            label: opening.args.to_string(),
+            position: pass.original_range(range),
+        }),
+        "details" => {
+            let (summary, open) = match opening.html {
+                true => (opening.summary.unwrap_or(""), opening.args == "open"),
+                false => (opening.args, false),
+            };
+            Some(Embed::Details {
+                summary: match summary {
+                    "" => "Details".to_string(),
+                    summary => summary.to_string(),
+                },
+                open,
+                position: pass.original_range(range),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// replace the fences of the known containers by embeds
+pub(crate) fn containers(pass: &mut Pass) {
+    let source = pass.source;
+    let code: Vec<Range<usize>> = pass
+        .events()
+        .into_iter()
+        .filter_map(|(event, range)| matches!(event, Event::Start(Tag::CodeBlock(_))).then_some(range))
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        lines.push(start..start + line.len());
+        start += line.len();
+    }
+
+    let mut stack: Vec<Opening> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].clone();
+        i += 1;
+        if code.iter().any(|c| c.contains(&line.start)) {
+            continue;
+        }
+        match parse_fence(&source[line.clone()]) {
+            Some(Fence::Open { name, args, html }) => {
+                let mut opening = Opening {
+                    name,
+                    args,
+                    html,
+                    summary: None,
+                    lines: line,
+                    tabs: Vec::new(),
+                };
+                if html {
+                    if let Some(summary) = lines.get(i).and_then(|l| parse_summary(&source[l.clone()])) {
+                        opening.summary = Some(summary);
+                        opening.lines.end = lines[i].end;
+                        i += 1;
+                    }
+                }
+                stack.push(opening)
+            }
+            Some(Fence::Close { html }) => {
+                if !stack.last().is_some_and(|o| o.html == html) {
+                    continue;
+                }
+                let opening = stack.pop().unwrap();
+                let range = opening.lines.start..line.end;
+                if let Some(embed) = embed(pass, &opening, stack.last_mut(), range) {
+                    let tag = pass.open_tag(embed);
+                    pass.replace(opening.lines, format!("\n{tag}\n\n"));
+                    pass.replace(line, format!("\n{CLOSE_TAG}\n\n"));
+                }
+            }
+            None => {}
+        }
+    }
+
+    // the containers that are not closed end with the document
+    while let Some(opening) = stack.pop() {
+        let range = opening.lines.start..source.len();
+        if let Some(embed) = embed(pass, &opening, stack.last_mut(), range) {
+            let tag = pass.open_tag(embed);
+            pass.replace(opening.lines, format!("\n{tag}\n\n"));
+            pass.replace(source.len()..source.len(), format!("\n\n{CLOSE_TAG}\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn prepare(src: &'static str) -> Prepared {
+        Prepared::new(&yew::props!(Props { src }))
+    }
+
+    #[wasm_bindgen_test]
+    fn unclosed_containers_end_with_the_document() {
+        let prepared = prepare(":::tabs\n:::tab A\none\n");
+        assert_eq!(
+            prepared.source,
+            "\n<YewMdEmbed id=\"1\">\n\n\n<YewMdEmbed id=\"0\">\n\none\n\n\n</YewMdEmbed>\n\n\n</YewMdEmbed>\n"
+        );
+        assert!(matches!(&prepared.embeds[0], Embed::Tab { index: 0, position } if *position == (8..21)));
+        assert!(matches!(&prepared.embeds[1], Embed::Tabs { titles } if titles == &["A"]));
+        let content = prepared.source.find("one").unwrap();
+        assert_eq!(prepared.original_range(content..content + 3), 17..20);
+    }
+
+    #[wasm_bindgen_test]
+    fn unmatched_fences_stay() {
+        for src in [":::\ntext\n", "</details>\n\ntext\n", ":::unknown\ntext\n:::\n"] {
+            let prepared = prepare(src);
+            assert_eq!(prepared.source, src);
+            assert!(prepared.embeds.is_empty());
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn html_details_with_a_summary() {
+        let prepared = prepare("<details open>\n<summary>More</summary>\n\n*text*\n\n</details>\n");
+        assert_eq!(
+            prepared.source,
+            "\n<YewMdEmbed id=\"0\">\n\n\n*text*\n\n\n</YewMdEmbed>\n\n"
+        );
+        assert!(matches!(
+            &prepared.embeds[0],
+            Embed::Details { summary, open: true, position } if summary == "More" && *position == (0..59)
+        ));
+    }
+}
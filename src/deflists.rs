@@ -0,0 +1,227 @@
+//! definition lists, with the syntax of Pandoc and PHP Markdown Extra:
+//!
+//! ```markdown
+//! Term
+//! : definition
+//!
+//! Other term
+//!
+//! :   first paragraph of the definition
+//!
+//!     second paragraph
+//! ```
+//!
+//! Only the lists that are not in a block quote or a list item are found.
+
+use core::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::prepare::{strip_indent, Pass};
+use crate::source::Piece;
+
+struct Item {
+    term: usize,
+    /// the lines of each definition
+    definitions: Vec<Range<usize>>,
+}
+
+struct Lines<'s> {
+    source: &'s str,
+    lines: Vec<Range<usize>>,
+    /// the fenced code blocks, that can not have terms
+    code: Vec<Range<usize>>,
+}
+
+impl<'s> Lines<'s> {
+    fn get(&self, i: usize) -> Option<&'s str> {
+        self.lines.get(i).map(|l| &self.source[l.clone()])
+    }
+
+    fn is_blank(&self, i: usize) -> bool {
+        self.get(i).is_some_and(|l| l.trim().is_empty())
+    }
+
+    fn is_indented(&self, i: usize) -> bool {
+        self.get(i).is_some_and(|l| l.starts_with("    ") || l.starts_with('\t'))
+    }
+
+    /// a line starting with `: `
+    fn is_marker(&self, i: usize) -> bool {
+        self.get(i).is_some_and(|l| {
+            let content = l.trim_start_matches(' ');
+            l.len() - content.len() <= 3 && (content.starts_with(": ") || content.starts_with(":\t"))
+        })
+    }
+
+    fn is_term(&self, i: usize) -> bool {
+        let Some(line) = self.lines.get(i) else {
+            return false;
+        };
+        let text = &self.source[line.clone()];
+        !text.trim().is_empty()
+            && !text.starts_with([' ', '\t', ':', '<'])
+            && !self.code.iter().any(|c| c.contains(&line.start))
+    }
+
+    /// the line of the first definition, if `i` is a term
+    fn first_definition(&self, i: usize) -> Option<usize> {
+        if !self.is_term(i) {
+            return None;
+        }
+        let j = if self.is_blank(i + 1) { i + 2 } else { i + 1 };
+        self.is_marker(j).then_some(j)
+    }
+
+    /// the lines of the definition starting at `start`
+    fn definition(&self, start: usize) -> Range<usize> {
+        let mut k = start + 1;
+        while k < self.lines.len() && !self.is_marker(k) {
+            if self.is_blank(k) {
+                // the definition goes on after blank lines if the next line is indented
+                let next = (k..self.lines.len()).find(|&m| !self.is_blank(m));
+                match next {
+                    Some(m) if self.is_indented(m) => k = m,
+                    _ => break,
+                }
+            } else if self.is_indented(k) {
+                k += 1;
+            } else if !self.is_blank(k - 1) && self.first_definition(k).is_none() {
+                // a lazy continuation line
+                k += 1;
+            } else {
+                break;
+            }
+        }
+        start..k
+    }
+
+    /// the items of the list starting at `i`
+    fn items(&self, mut i: usize) -> Vec<Item> {
+        let mut items = Vec::new();
+        while let Some(mut k) = self.first_definition(i) {
+            let mut definitions = Vec::new();
+            while self.is_marker(k) {
+                let definition = self.definition(k);
+                k = definition.end;
+                definitions.push(definition);
+            }
+            items.push(Item { term: i, definitions });
+            i = (k..self.lines.len()).find(|&m| !self.is_blank(m)).unwrap_or(k);
+        }
+        items
+    }
+}
+
+/// the pieces of a definition, without its marker and indentation
+fn definition_pieces(lines: &Lines, definition: Range<usize>) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    for i in definition {
+        let range = lines.lines[i].clone();
+        let line = &lines.source[range.clone()];
+        let content = match pieces.is_empty() {
+            true => line.trim_start_matches(' ')[1..].trim_start_matches([' ', '\t']),
+            false => strip_indent(line),
+        };
+        pieces.push(Piece::Copy(range.end - content.len()..range.end));
+    }
+    pieces
+}
+
+/// replace the definition lists by embeds
+pub(crate) fn definition_lists(pass: &mut Pass) {
+    let source = pass.source;
+    let code = pass
+        .events()
+        .into_iter()
+        .filter_map(|(event, range)| {
+            matches!(event, Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_)))).then_some(range)
+        })
+        .collect();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        lines.push(start..start + line.len());
+        start += line.len();
+    }
+    let lines = Lines { source, lines, code };
+
+    let mut i = 0;
+    while i < lines.lines.len() {
+        let items = match i == 0 || lines.is_blank(i - 1) {
+            true => lines.items(i),
+            false => Vec::new(),
+        };
+        let Some(last) = items.last().and_then(|item| item.definitions.last()) else {
+            i += 1;
+            continue;
+        };
+        let list_start = lines.lines[i].start;
+        let list_end = lines.lines[last.end - 1].end;
+        i = last.end;
+
+        let position = pass.original_range(list_start..list_end);
+        let mut pieces = vec![Piece::Text(format!(
+            "\n{}\n\n",
+            pass.open_tag(Embed::DefinitionList { position })
+        ))];
+        for item in items {
+            let term = lines.lines[item.term].clone();
+            let term_end = term.start + source[term.clone()].trim_end().len();
+            pieces.push(Piece::Text(format!("{}\n\n", pass.open_tag(Embed::Term))));
+            pieces.push(Piece::Copy(term.start..term_end));
+            pieces.push(Piece::Text(format!("\n\n{CLOSE_TAG}\n\n")));
+            for definition in item.definitions {
+                pieces.push(Piece::Text(format!("{}\n\n", pass.open_tag(Embed::Definition))));
+                pieces.extend(definition_pieces(&lines, definition));
+                pieces.push(Piece::Text(format!("\n\n{CLOSE_TAG}\n\n")));
+            }
+        }
+        pieces.push(Piece::Text(format!("{CLOSE_TAG}\n")));
+        pass.replace_with(list_start..list_end, pieces);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn prepare(src: &'static str) -> Prepared {
+        Prepared::new(&yew::props!(Props { src, definition_lists: true }))
+    }
+
+    #[wasm_bindgen_test]
+    fn lazy_lines_and_indented_paragraphs() {
+        let prepared = prepare("Term\n: first line\nlazy line\n\nOther\n:   para\n\n    second\n\nAfter\n");
+        assert_eq!(
+            prepared.source,
+            "\n<YewMdEmbed id=\"0\">\n\n<YewMdEmbed id=\"1\">\n\nTerm\n\n</YewMdEmbed>\n\n<YewMdEmbed id=\"2\">\n\nfirst line\nlazy line\n\n\n</YewMdEmbed>\n\n<YewMdEmbed id=\"3\">\n\nOther\n\n</YewMdEmbed>\n\n<YewMdEmbed id=\"4\">\n\npara\n\nsecond\n\n\n</YewMdEmbed>\n\n</YewMdEmbed>\n\nAfter\n"
+        );
+        assert!(matches!(&prepared.embeds[0], Embed::DefinitionList { position } if *position == (0..56)));
+        let lazy = prepared.source.find("lazy line").unwrap();
+        assert_eq!(prepared.original_range(lazy..lazy + 9), 18..27);
+        let second = prepared.source.find("second").unwrap();
+        assert_eq!(prepared.original_range(second..second + 6), 49..55);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_line_before_a_definition_is_a_term() {
+        // `Two` is not a lazy line of the first definition, but the next term
+        let prepared = prepare("One\n: first\nTwo\n: second\n");
+        assert_eq!(prepared.embeds.len(), 5);
+        assert!(matches!(prepared.embeds[3], Embed::Term));
+        let term = prepared.source.find("Two").unwrap();
+        assert_eq!(prepared.original_range(term..term + 3), 12..15);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_paragraph_is_not_a_term() {
+        let src = "Some text\nmore text\n: not a definition\n";
+        assert_eq!(prepare(src).source, src);
+    }
+}
@@ -0,0 +1,461 @@
+//! the constructs that yew-markdown renders itself, see [`crate::prepare`].
+
+use core::ops::Range;
+
+use yew::prelude::{classes, html, AttrValue, Html};
+
+use crate::bibliography::BibEntry;
+use crate::citations::CitationItem;
+use crate::footnotes::{reference_id, FootnoteRef};
+use crate::glossary::GlossaryTerm;
+use crate::inline::InlineTag;
+use crate::spoilers::Spoiler;
+#[cfg(feature = "math")]
+use crate::stylesheets::KATEX_STYLESHEET;
+use crate::tabs::{TabGroup, TabPanel};
+use crate::xref::TargetKind;
+use crate::{Callout, CodeBlock, ComponentCreationError, MathBlock, MdComponentProps, MdContext};
+
+/// name of the custom component that stands for an [`Embed`].
+pub(crate) const EMBED_TAG: &str = "YewMdEmbed";
+
+/// closes an [`EMBED_TAG`] that has children
+pub(crate) const CLOSE_TAG: &str = "</YewMdEmbed>";
+
+pub(crate) enum Embed {
+    /// a code block, rendered by the `code_highlighter`
+    Code(CodeBlock),
+    /// a formula, rendered by the `math_renderer`
+    Math(MathBlock),
+    /// a display formula with a label, and its number
+    Equation {
+        math: MathBlock,
+        label: String,
+        number: usize,
+    },
+    /// a link to a numbered element
+    Reference {
+        label: String,
+        text: String,
+        position: Range<usize>,
+    },
+    /// an image with a caption. The caption is either the children,
+    /// or the alternative text
+    Figure {
+        src: String,
+        title: String,
+        alt: String,
+        caption: Option<String>,
+        label: Option<String>,
+        number: usize,
+        position: Range<usize>,
+    },
+    /// a table, and its caption in the children
+    TableFigure { label: Option<String> },
+    /// the caption of a figure or a table, in the children
+    Caption { kind: TargetKind, number: usize },
+    /// `==marked==`, `~sub~`, `^sup^`, `++inserted++` or `||spoiler||`, with its content in the children
+    Inline {
+        tag: InlineTag,
+        position: Range<usize>,
+    },
+    /// an abbreviation, explained by its title
+    Abbreviation {
+        text: String,
+        title: String,
+        position: Range<usize>,
+    },
+    /// an emoji of `custom_emoji`
+    Emoji {
+        name: String,
+        src: AttrValue,
+        position: Range<usize>,
+    },
+    /// a term of the `glossary`, as written in the text
+    GlossaryTerm {
+        text: String,
+        term: String,
+        position: Range<usize>,
+    },
+    /// a block quote starting with `[!KIND]`, with its content in the children
+    Callout {
+        kind: AttrValue,
+        title: AttrValue,
+        icon: AttrValue,
+        position: Range<usize>,
+    },
+    /// a block that can be folded, with its content in the children
+    Details {
+        summary: String,
+        open: bool,
+        position: Range<usize>,
+    },
+    /// a definition list, with its terms and definitions in the children
+    DefinitionList { position: Range<usize> },
+    /// a term of a definition list, in the children
+    Term,
+    /// a definition of a term, in the children
+    Definition,
+    /// a `:::spoiler` container, with its content in the children
+    SpoilerBlock { label: String, position: Range<usize> },
+    /// a group of tabs, with the tabs in the children
+    Tabs { titles: Vec<String> },
+    /// the content of a tab, in the children
+    Tab { index: usize, position: Range<usize> },
+    /// a heading and its section, that can be folded
+    Section,
+    /// the heading of a [`Embed::Section`], in the children
+    SectionSummary,
+    /// the number of a heading, like `1.2.3`
+    SectionNumber { number: String },
+    /// a citation of works of the `bibliography`
+    Citation {
+        items: Vec<CitationItem>,
+        position: Range<usize>,
+    },
+    /// the works that are cited, at the end of the document
+    Bibliography { entries: Vec<BibEntry> },
+    /// a link to a footnote
+    FootnoteRef {
+        id: String,
+        number: usize,
+        /// 1 for the first reference to this footnote, 2 for the second...
+        occurrence: usize,
+        position: Range<usize>,
+    },
+    /// the list of footnotes, in the children
+    Footnotes,
+    /// a footnote, with its content in the children
+    FootnoteItem {
+        id: String,
+        number: usize,
+        references: usize,
+    },
+}
+
+impl<'a> MdContext<'a> {
+    /// render `<YewMdEmbed id="..."/>`
+    pub(crate) fn render_embed(self, input: MdComponentProps) -> Result<Html, ComponentCreationError> {
+        let embed = input
+            .get_parsed_optional::<usize>("id")?
+            .and_then(|id| self.prepared.embeds.get(id));
+
+        let Some(embed) = embed else {
+            return Ok(html! {});
+        };
+
+        Ok(match *embed {
+            Embed::Code(ref block) => {
+                let inside = match &self.props.code_highlighter {
+                    Some(f) => f.emit(block.clone()),
+                    #[cfg(feature = "highlight")]
+                    None if self.props.class_highlighting => block.highlighted_with_classes(),
+                    None => block.plain(),
+                };
+                html! {
+                    <div onclick={self.handler(block.position.clone(), true)}>{inside}</div>
+                }
+            }
+            Embed::Math(ref block) => self.render_math(block),
+            Embed::Equation {
+                ref math,
+                ref label,
+                number,
+            } => {
+                self.require_stylesheet();
+                html! {
+                    <div class="md-equation" id={label.clone()}>
+                        {self.render_math(math)}
+                        <span class="md-equation-number">{format!("({number})")}</span>
+                    </div>
+                }
+            }
+            Embed::Reference {
+                ref label,
+                ref text,
+                ref position,
+            } => html! {
+                <a class="md-ref" href={format!("#{label}")} onclick={self.handler(position.clone(), true)}>
+                    {text.clone()}
+                </a>
+            },
+            Embed::Figure {
+                ref src,
+                ref title,
+                ref alt,
+                ref caption,
+                ref label,
+                number,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                let title = (!title.is_empty()).then(|| title.clone());
+                let caption = match caption {
+                    Some(caption) => html! {
+                        <figcaption class="md-caption">
+                            <span class="md-caption-label">{format!("Figure {number}:")}</span>
+                            {" "}{caption.clone()}
+                        </figcaption>
+                    },
+                    None => input.children,
+                };
+                html! {
+                    <figure class="md-figure" id={label.clone()}>
+                        <img src={src.clone()} alt={alt.clone()} title={title}
+                            onclick={self.handler(position.clone(), true)}/>
+                        {caption}
+                    </figure>
+                }
+            }
+            Embed::TableFigure { ref label } => html! {
+                <figure class="md-table" id={label.clone()}>{input.children}</figure>
+            },
+            Embed::Caption { kind, number } => {
+                self.require_stylesheet();
+                html! {
+                    <figcaption class="md-caption">
+                        <span class="md-caption-label">{format!("{} {number}:", kind.name())}</span>
+                        {" "}{input.children}
+                    </figcaption>
+                }
+            }
+            Embed::Inline {
+                tag: InlineTag::Spoiler,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                html! {
+                    <Spoiler block=false onclick={self.handler(position.clone(), true)}>{input.children}</Spoiler>
+                }
+            }
+            Embed::Inline { tag, ref position } => html! {
+                <@{tag.name()} onclick={self.handler(position.clone(), true)}>{input.children}</@>
+            },
+            Embed::Abbreviation {
+                ref text,
+                ref title,
+                ref position,
+            } => html! {
+                <abbr title={title.clone()} onclick={self.handler(position.clone(), true)}>{text.clone()}</abbr>
+            },
+            Embed::Emoji {
+                ref name,
+                ref src,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                let shortcode = format!(":{name}:");
+                html! {
+                    <img class="md-emoji" src={src.clone()} alt={shortcode.clone()} title={shortcode}
+                        onclick={self.handler(position.clone(), true)}/>
+                }
+            }
+            Embed::GlossaryTerm {
+                ref text,
+                ref term,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                let definition = self
+                    .props
+                    .glossary
+                    .as_ref()
+                    .and_then(|g| g.get(term).cloned())
+                    .unwrap_or_default();
+                html! {
+                    <GlossaryTerm text={text.clone()} {definition}
+                        onclick={self.handler(position.clone(), true)}/>
+                }
+            }
+            Embed::Callout {
+                ref kind,
+                ref title,
+                ref icon,
+                ref position,
+            } => {
+                let onclick = self.handler(position.clone(), true);
+                if let Some(f) = &self.props.callout_renderer {
+                    let callout = Callout {
+                        kind: kind.clone(),
+                        title: title.clone(),
+                        icon: icon.clone(),
+                        content: input.children,
+                        position: position.clone(),
+                    };
+                    return Ok(html! {<div {onclick}>{f.emit(callout)}</div>});
+                }
+                self.require_stylesheet();
+                html! {
+                    <div class={classes!("md-callout", format!("md-callout-{kind}"))} {onclick}>
+                        <p class="md-callout-title">
+                            <span class="md-callout-icon" aria-hidden="true">{icon.clone()}</span>
+                            {title.clone()}
+                        </p>
+                        {input.children}
+                    </div>
+                }
+            }
+            Embed::Details {
+                ref summary,
+                open,
+                ref position,
+            } => html! {
+                <details class="md-details" {open}>
+                    <summary onclick={self.handler(position.clone(), true)}>{summary.clone()}</summary>
+                    {input.children}
+                </details>
+            },
+            Embed::DefinitionList { ref position } => {
+                self.require_stylesheet();
+                html! {
+                    <dl class="md-dl" onclick={self.handler(position.clone(), true)}>{input.children}</dl>
+                }
+            }
+            Embed::Term => html! {<dt>{input.children}</dt>},
+            Embed::Definition => html! {<dd>{input.children}</dd>},
+            Embed::SpoilerBlock {
+                ref label,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                let label = (!label.is_empty()).then(|| label.clone());
+                html! {
+                    <Spoiler block=true {label} onclick={self.handler(position.clone(), true)}>
+                        {input.children}
+                    </Spoiler>
+                }
+            }
+            Embed::Tabs { ref titles } => {
+                self.require_stylesheet();
+                html! {
+                    <TabGroup titles={titles.clone()} sync={self.props.sync_tabs}>
+                        {input.children}
+                    </TabGroup>
+                }
+            }
+            Embed::Tab { index, ref position } => html! {
+                <TabPanel {index}>
+                    <div onclick={self.handler(position.clone(), true)}>{input.children}</div>
+                </TabPanel>
+            },
+            Embed::Section => {
+                self.require_stylesheet();
+                html! {
+                    <details class="md-section" open=true>{input.children}</details>
+                }
+            }
+            Embed::SectionSummary => html! {
+                <summary>{input.children}</summary>
+            },
+            Embed::SectionNumber { ref number } => {
+                self.require_stylesheet();
+                html! {<span class="md-section-number">{number.clone()}</span>}
+            }
+            Embed::Citation {
+                ref items,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                let item = |item: &CitationItem| match &item.key {
+                    Some(key) => html! {<a href={format!("#ref-{key}")}>{item.text.clone()}</a>},
+                    None => html! {<span class="md-citation-unknown">{item.text.clone()}</span>},
+                };
+                html! {
+                    <span class="md-citation" onclick={self.handler(position.clone(), true)}>
+                        {"("}
+                        {for items.iter().enumerate().map(|(i, it)| html! {
+                            <>{if i > 0 { "; " } else { "" }}{item(it)}</>
+                        })}
+                        {")"}
+                    </span>
+                }
+            }
+            Embed::Bibliography { ref entries } => {
+                self.require_stylesheet();
+                html! {
+                    <section class="md-bibliography" role="doc-bibliography">
+                        <h2>{"References"}</h2>
+                        <ul>{for entries.iter().map(bibliography_entry)}</ul>
+                    </section>
+                }
+            }
+            Embed::FootnoteRef {
+                ref id,
+                number,
+                occurrence,
+                ref position,
+            } => {
+                self.require_stylesheet();
+                html! {
+                    <FootnoteRef id={id.clone()} {number} {occurrence}
+                        preview={self.props.footnote_previews}
+                        onclick={self.handler(position.clone(), true)}/>
+                }
+            }
+            Embed::Footnotes => {
+                self.require_stylesheet();
+                html! {
+                    <section class="md-footnotes" role="doc-endnotes">
+                        <hr/>
+                        <ol>{input.children}</ol>
+                    </section>
+                }
+            }
+            Embed::FootnoteItem {
+                ref id,
+                number,
+                references,
+            } => html! {
+                <li id={format!("fn-{id}")} value={number.to_string()}>
+                    <div class="md-footnote-content" id={format!("fn-{id}-content")}>{input.children}</div>
+                    {for (1..=references).map(|occurrence| html! {
+                        <a class="md-footnote-backref" href={format!("#{}", reference_id(id, occurrence))}
+                            aria-label="Back to the reference">{"↩"}</a>
+                    })}
+                </li>
+            },
+        })
+    }
+
+    fn render_math(self, block: &MathBlock) -> Html {
+        let inside = match &self.props.math_renderer {
+            Some(f) => f.emit(block.clone()),
+            #[cfg(feature = "math")]
+            None => {
+                self.require_link("stylesheet", KATEX_STYLESHEET, "", "anonymous");
+                block.katex()
+            }
+            #[cfg(not(feature = "math"))]
+            None => block.error("no math renderer"),
+        };
+        let class = if block.display { "math-flow" } else { "math-inline" };
+        html! {
+            <span class={class} onclick={self.handler(block.position.clone(), true)}>{inside}</span>
+        }
+    }
+}
+
+/// `Knuth, Donald E. 1984. *Literate Programming*. The Computer Journal.`
+fn bibliography_entry(entry: &BibEntry) -> Html {
+    let authors = entry
+        .authors
+        .iter()
+        .map(|a| a.full_name())
+        .collect::<Vec<_>>()
+        .join("; ");
+    html! {
+        <li id={format!("ref-{}", entry.key)}>
+            if !authors.is_empty() {
+                {format!("{authors}. ")}
+            }
+            {format!("{}. ", entry.year.as_deref().unwrap_or("n.d."))}
+            <em>{entry.title.clone()}</em>{". "}
+            if let Some(container) = &entry.container {
+                {format!("{container}. ")}
+            }
+            if let Some(url) = &entry.url {
+                <a href={url.clone()}>{url.clone()}</a>
+            }
+        </li>
+    }
+}
@@ -0,0 +1,55 @@
+//! emoji shortcodes, like `:rocket:`, outside of code.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pulldown_cmark::Tag;
+use yew::prelude::AttrValue;
+
+use crate::embed::Embed;
+use crate::prepare::Pass;
+
+/// the name in a shortcode at the start of `text`, and the length of the shortcode
+fn parse_shortcode(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix(':')?;
+    let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || "_+-".contains(c)))?;
+    if end == 0 || !rest[end..].starts_with(':') {
+        return None;
+    }
+    Some((&rest[..end], end + 2))
+}
+
+#[cfg(feature = "emoji")]
+fn unicode(name: &str) -> Option<&'static str> {
+    emojis::get_by_shortcode(name).map(|e| e.as_str())
+}
+
+#[cfg(not(feature = "emoji"))]
+fn unicode(_name: &str) -> Option<&'static str> {
+    None
+}
+
+/// replace the shortcodes by their emoji.
+/// With `builtin` false, only the `custom` ones are replaced.
+pub(crate) fn emoji(pass: &mut Pass, builtin: bool, custom: Option<&Rc<HashMap<String, AttrValue>>>) {
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..)));
+    // an unknown shortcode is not a match: its closing `:` can start the next one
+    let found = pass.scan(&runs, |_, text| {
+        let (name, len) = parse_shortcode(text)?;
+        let known = custom.is_some_and(|c| c.contains_key(name)) || (builtin && unicode(name).is_some());
+        known.then_some((len, name))
+    });
+    for (range, name) in found {
+        if let Some(src) = custom.and_then(|c| c.get(name)) {
+            let embed = Embed::Emoji {
+                name: name.to_string(),
+                src: src.clone(),
+                position: pass.original_range(range.clone()),
+            };
+            pass.embed_inline(range, embed);
+        } else if let Some(emoji) = unicode(name) {
+            pass.replace(range, emoji);
+        }
+    }
+}
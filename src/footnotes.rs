@@ -0,0 +1,256 @@
+//! footnotes: `[^label]` is a numbered link to the definition `[^label]: ...`.
+//!
+//! The definitions are moved to a list at the end of the document, numbered
+//! in the order of their first reference, each with links back to its references.
+
+use core::ops::Range;
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Tag};
+use wasm_bindgen::JsCast;
+use web_sys::{window, Element};
+use yew::prelude::{
+    function_component, html, use_state, AttrValue, Callback, FocusEvent, Html, MouseEvent, Properties,
+};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::prepare::{strip_indent, Pass};
+use crate::source::Piece;
+
+/// the numbers of the footnotes, by label
+#[derive(Default)]
+pub(crate) struct Footnotes {
+    numbers: HashMap<String, usize>,
+}
+
+/// labels are matched without case, like link references
+fn key(label: &str) -> String {
+    label.to_lowercase()
+}
+
+/// the id of the elements of a footnote
+fn id(label: &str) -> String {
+    key(label).split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// the id of the `occurrence`-th reference to a footnote, starting at 1
+pub(crate) fn reference_id(id: &str, occurrence: usize) -> String {
+    match occurrence {
+        1 => format!("fnref-{id}"),
+        _ => format!("fnref-{id}-{occurrence}"),
+    }
+}
+
+/// the content of the definition at `range`, without its label and its indentation
+fn definition_content(source: &str, range: Range<usize>) -> Vec<Piece> {
+    let text = &source[range.clone()];
+    let Some(colon) = text.find("]:") else {
+        return Vec::new();
+    };
+    let first_line = text[colon + 2..].trim_start_matches([' ', '\t']);
+    let mut start = range.end - first_line.len();
+    let mut pieces = Vec::new();
+    for (i, line) in source[start..range.end].split_inclusive('\n').enumerate() {
+        let content = match i {
+            0 => line,
+            _ => strip_indent(line),
+        };
+        let content_start = start + line.len() - content.len();
+        start += line.len();
+        pieces.push(Piece::Copy(content_start..start));
+    }
+    pieces
+}
+
+/// move the definitions to the end of the document
+pub(crate) fn definitions(pass: &mut Pass, footnotes: &mut Footnotes) {
+    let events = pass.events();
+    let mut definitions: Vec<(String, Range<usize>)> = Vec::new();
+    for (event, range) in &events {
+        let Event::Start(Tag::FootnoteDefinition(label)) = event else {
+            continue;
+        };
+        // only the first definition is used, the others are removed
+        if definitions.iter().any(|(l, _)| *l == key(label)) {
+            pass.diagnostic(range.clone(), format!("the footnote `{label}` is defined more than once"));
+            pass.replace(range.clone(), "");
+            continue;
+        }
+        definitions.push((key(label), range.clone()));
+    }
+    if definitions.is_empty() {
+        return;
+    }
+
+    // the references in the text come first, then the ones in the definitions,
+    // that will be after the text
+    let in_definition = |x: usize| definitions.iter().any(|(_, d)| d.contains(&x));
+    let (mut references, in_definitions): (Vec<_>, Vec<_>) = events
+        .iter()
+        .filter_map(|(event, range)| match event {
+            Event::FootnoteReference(label) => Some((key(label), range.start)),
+            _ => None,
+        })
+        .partition(|(_, start)| !in_definition(*start));
+    references.extend(in_definitions);
+
+    let labels = references
+        .into_iter()
+        .map(|(label, _)| label)
+        .chain(definitions.iter().map(|(label, _)| label.clone()));
+    for label in labels {
+        if definitions.iter().any(|(l, _)| *l == label) {
+            let number = footnotes.numbers.len() + 1;
+            footnotes.numbers.entry(label).or_insert(number);
+        }
+    }
+    definitions.sort_by_key(|(label, _)| footnotes.numbers[label]);
+
+    let source = pass.source;
+    let mut pieces = vec![Piece::Text(format!("\n\n{}\n\n", pass.open_tag(Embed::Footnotes)))];
+    for (label, range) in definitions {
+        let item = Embed::FootnoteItem {
+            id: id(&label),
+            number: footnotes.numbers[&label],
+            references: events
+                .iter()
+                .filter(|(e, _)| matches!(e, Event::FootnoteReference(l) if key(l) == label))
+                .count(),
+        };
+        pieces.push(Piece::Text(format!("{}\n\n", pass.open_tag(item))));
+        pieces.extend(definition_content(source, range.clone()));
+        pieces.push(Piece::Text(format!("\n\n{CLOSE_TAG}\n\n")));
+        pass.replace(range, "");
+    }
+    pieces.push(Piece::Text(format!("{CLOSE_TAG}\n")));
+    pass.replace_with(source.len()..source.len(), pieces);
+}
+
+/// replace the references by links to the definitions
+pub(crate) fn references(pass: &mut Pass, footnotes: &Footnotes) {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for (event, range) in pass.events() {
+        let Event::FootnoteReference(label) = event else {
+            continue;
+        };
+        let Some(&number) = footnotes.numbers.get(&key(&label)) else {
+            pass.diagnostic(range, format!("the footnote `{label}` is not defined"));
+            continue;
+        };
+        let occurrence = occurrences.entry(key(&label)).or_default();
+        *occurrence += 1;
+        let embed = Embed::FootnoteRef {
+            id: id(&label),
+            number,
+            occurrence: *occurrence,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, embed);
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub(crate) struct FootnoteRefProps {
+    pub id: String,
+    pub number: usize,
+    pub occurrence: usize,
+    /// show the content of the footnote when the reference is hovered or focused
+    pub preview: bool,
+    pub onclick: Callback<MouseEvent>,
+}
+
+/// a reference to a footnote
+#[function_component]
+pub(crate) fn FootnoteRef(props: &FootnoteRefProps) -> Html {
+    let preview = use_state(|| None::<String>);
+
+    let link = html! {
+        <a href={format!("#fn-{}", props.id)} onclick={props.onclick.clone()}>
+            {props.number}
+        </a>
+    };
+    if !props.preview {
+        return html! {
+            <sup class="md-footnote-ref" id={reference_id(&props.id, props.occurrence)}>{link}</sup>
+        };
+    }
+
+    // the content is copied from the list of footnotes, where it is already rendered,
+    // without its ids: they are already in the document
+    let show = {
+        let preview = preview.clone();
+        let content_id = format!("fn-{}-content", props.id);
+        Callback::from(move |()| {
+            let content = window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.get_element_by_id(&content_id))
+                .and_then(|e| e.clone_node_with_deep(true).ok())
+                .and_then(|copy| copy.dyn_into::<Element>().ok())
+                .map(|copy| {
+                    while let Ok(Some(e)) = copy.query_selector("[id]") {
+                        if e.remove_attribute("id").is_err() {
+                            break;
+                        }
+                    }
+                    copy.inner_html()
+                });
+            preview.set(content)
+        })
+    };
+    let hide = {
+        let preview = preview.clone();
+        Callback::from(move |()| preview.set(None))
+    };
+    let popover = preview.as_ref().map(|content| {
+        html! {
+            <span class="md-footnote-preview" role="tooltip">
+                {Html::from_html_unchecked(AttrValue::from(content.clone()))}
+            </span>
+        }
+    });
+    html! {
+        <sup class="md-footnote-ref" id={reference_id(&props.id, props.occurrence)}
+            onmouseenter={show.reform(|_: MouseEvent| ())} onmouseleave={hide.reform(|_: MouseEvent| ())}
+            onfocusin={show.reform(|_: FocusEvent| ())} onfocusout={hide.reform(|_: FocusEvent| ())}>
+            {link}
+            {popover}
+        </sup>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    #[wasm_bindgen_test]
+    fn a_footnote_defined_twice() {
+        let src = "A[^n] and B[^N].\n\n[^n]: First.\n\n[^N]: Second.\n";
+        let prepared = Prepared::new(&yew::props!(Props { src }));
+        assert_eq!(prepared.diagnostics.len(), 1);
+        assert_eq!(prepared.diagnostics[0].position.start, 32);
+        assert!(!prepared.source.contains("Second"));
+
+        // the labels are matched without case: both references are to the first definition
+        assert!(matches!(
+            &prepared.embeds[1],
+            Embed::FootnoteItem { id, number: 1, references: 2 } if id == "n"
+        ));
+        let references: Vec<_> = prepared
+            .embeds
+            .iter()
+            .filter_map(|e| match e {
+                Embed::FootnoteRef { occurrence, position, .. } => Some((*occurrence, position.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(references, [(1, 1..5), (2, 11..15)]);
+
+        // the definition moved to the end still points to its place in the source
+        let content = prepared.source.find("First.").unwrap();
+        assert_eq!(prepared.original_range(content..content + 6), 24..30);
+    }
+}
@@ -0,0 +1,107 @@
+//! the terms of the `glossary`, explained in a popover where they appear in the text.
+//! They are not looked for in code, links and headings.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use pulldown_cmark::Tag;
+use yew::prelude::{function_component, html, use_state, Callback, FocusEvent, Html, MouseEvent, Properties};
+
+use crate::abbreviations::is_word_char;
+use crate::embed::Embed;
+use crate::prepare::Pass;
+
+/// a term of the glossary, in lowercase
+struct Term<'g> {
+    term: &'g String,
+    lowercase: String,
+    chars: usize,
+}
+
+impl<'g> Term<'g> {
+    fn new(term: &'g String) -> Self {
+        Self {
+            term,
+            lowercase: term.to_lowercase(),
+            chars: term.chars().count(),
+        }
+    }
+}
+
+/// the longest term at the start of `text` that is a whole word, without case,
+/// and its length in `text`
+fn find<'g>(text: &str, terms: &[Term<'g>]) -> Option<(&'g String, usize)> {
+    let first = text.chars().next()?.to_lowercase().next();
+    terms
+        .iter()
+        // most positions can not start a term, they are rejected without allocating
+        .filter(|term| term.lowercase.chars().next() == first)
+        .filter_map(|term| {
+            let len = text.char_indices().nth(term.chars).map_or(text.len(), |(i, _)| i);
+            let candidate = &text[..len];
+            let found = candidate.chars().count() == term.chars
+                && candidate.to_lowercase() == term.lowercase
+                && !text[len..].starts_with(is_word_char);
+            found.then_some((term.term, len))
+        })
+        .max_by_key(|&(_, len)| len)
+}
+
+/// replace the terms of the glossary by embeds.
+/// Unless `all_occurrences` is true, only the first occurrence of each term is replaced.
+pub(crate) fn glossary(pass: &mut Pass, glossary: &Rc<HashMap<String, Html>>, all_occurrences: bool) {
+    let terms: Vec<Term> = glossary.keys().filter(|t| !t.is_empty()).map(Term::new).collect();
+    let mut found: HashSet<&String> = HashSet::new();
+    let source = pass.source;
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..) | Tag::Heading(..)));
+    let matches = pass.scan(&runs, |before, text| {
+        let (term, len) = find(text, &terms)
+            .filter(|_| !before.ends_with(is_word_char))
+            .filter(|(term, _)| all_occurrences || !found.contains(term))?;
+        found.insert(term);
+        Some((len, term.clone()))
+    });
+    for (range, term) in matches {
+        let embed = Embed::GlossaryTerm {
+            text: source[range.clone()].to_string(),
+            term,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, embed);
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub(crate) struct GlossaryTermProps {
+    pub text: String,
+    pub definition: Html,
+    pub onclick: Callback<MouseEvent>,
+}
+
+/// a term, with its definition shown when it is hovered or focused
+#[function_component]
+pub(crate) fn GlossaryTerm(props: &GlossaryTermProps) -> Html {
+    let shown = use_state(|| false);
+    let show = {
+        let shown = shown.clone();
+        Callback::from(move |()| shown.set(true))
+    };
+    let hide = {
+        let shown = shown.clone();
+        Callback::from(move |()| shown.set(false))
+    };
+    let popover = shown.then(|| {
+        html! {
+            <span class="md-glossary-definition" role="tooltip">{props.definition.clone()}</span>
+        }
+    });
+    html! {
+        <span class="md-glossary-term" tabindex="0" onclick={props.onclick.clone()}
+            onmouseenter={show.reform(|_: MouseEvent| ())} onmouseleave={hide.reform(|_: MouseEvent| ())}
+            onfocusin={show.reform(|_: FocusEvent| ())} onfocusout={hide.reform(|_: FocusEvent| ())}>
+            {props.text.clone()}
+            {popover}
+        </span>
+    }
+}
@@ -0,0 +1,95 @@
+//! code blocks rendered outside of `rust-web-markdown`
+
+use core::ops::Range;
+
+use yew::prelude::{html, Html};
+
+#[cfg(feature = "highlight")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "highlight")]
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// the prefix of the classes of highlighted tokens
+#[cfg(feature = "highlight")]
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "md-hl-" };
+
+#[cfg(feature = "highlight")]
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+#[cfg(feature = "highlight")]
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// a fenced or indented code block, as given to the `code_highlighter` of [`Props`](crate::Props).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeBlock {
+    /// the first word of the info string of a fenced code block, if any
+    pub language: Option<String>,
+
+    /// the content of the block
+    pub code: String,
+
+    /// the range of the whole block in the markdown source
+    pub position: Range<usize>,
+}
+
+impl CodeBlock {
+    /// render the code without any highlighting.
+    /// It is what code blocks look like without the `highlight` feature.
+    pub fn plain(&self) -> Html {
+        let class = self.language.as_ref().map(|l| format!("language-{l}"));
+        html! {
+            <pre><code class={class}>{self.code.clone()}</code></pre>
+        }
+    }
+
+    /// highlight the code with css classes instead of inline styles.
+    /// The colors come from the stylesheet given by [`theme_stylesheet`],
+    /// so the theme can change without rendering the code again.
+    ///
+    /// Falls back to [`CodeBlock::plain`] if the language is unknown.
+    #[cfg(feature = "highlight")]
+    pub fn highlighted_with_classes(&self) -> Html {
+        let syntax_set = syntax_set();
+        let syntax = match self.language.as_deref().and_then(|l| syntax_set.find_syntax_by_token(l)) {
+            Some(syntax) => syntax,
+            None => return self.plain(),
+        };
+
+        let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntax_set, CLASS_STYLE);
+        for line in LinesWithEndings::from(&self.code) {
+            if generator.parse_html_for_line_which_includes_newline(line).is_err() {
+                return self.plain();
+            }
+        }
+        let inner_html = generator.finalize();
+
+        html! {
+            <pre class="md-hl-code">
+                <code>{Html::from_html_unchecked(inner_html.into())}</code>
+            </pre>
+        }
+    }
+}
+
+/// the css that gives their colors to code blocks highlighted with classes,
+/// for one of the themes of the built-in highlighter (for example `"base16-ocean.dark"`
+/// or `"InspiredGitHub"`).
+///
+/// Returns `None` if there is no theme with this name.
+#[cfg(feature = "highlight")]
+pub fn theme_stylesheet(theme: &str) -> Option<String> {
+    let theme = theme_set().themes.get(theme)?;
+    css_for_theme_with_class_style(theme, CLASS_STYLE).ok()
+}
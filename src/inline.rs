@@ -0,0 +1,318 @@
+//! inline syntaxes that `pulldown-cmark` does not know:
+//! `==marked==`, `H~2~O`, `x^2^`, `++inserted++` and `||spoiler||`.
+
+use core::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::prepare::Pass;
+
+/// the inline syntaxes that are not in [`Options`](crate::Options).
+/// They are all disabled by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InlineExtensions {
+    /// `==marked==`, rendered as `<mark>`
+    pub mark: bool,
+    /// `H~2~O`, rendered as `<sub>`.
+    /// A single `~` is no longer a strikethrough when the content has no spaces.
+    pub subscript: bool,
+    /// `x^2^`, rendered as `<sup>`
+    pub superscript: bool,
+    /// `++inserted++`, rendered as `<ins>`
+    pub insert: bool,
+    /// `||spoiler||`, hidden until it is clicked
+    pub spoiler: bool,
+}
+
+impl InlineExtensions {
+    pub fn all() -> Self {
+        Self {
+            mark: true,
+            subscript: true,
+            superscript: true,
+            insert: true,
+            spoiler: true,
+        }
+    }
+
+    fn any(self) -> bool {
+        self.mark || self.subscript || self.superscript || self.insert || self.spoiler
+    }
+
+    /// the delimiters of the enabled syntaxes
+    pub(crate) fn delimiters(self) -> impl Iterator<Item = &'static str> {
+        InlineTag::ALL
+            .into_iter()
+            .filter(move |tag| tag.enabled(self))
+            .map(InlineTag::delimiter)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum InlineTag {
+    Mark,
+    Sub,
+    Sup,
+    Ins,
+    Spoiler,
+}
+
+impl InlineTag {
+    /// in the order they are tried
+    const ALL: [InlineTag; 5] = [
+        InlineTag::Mark,
+        InlineTag::Ins,
+        InlineTag::Spoiler,
+        InlineTag::Sub,
+        InlineTag::Sup,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InlineTag::Mark => "mark",
+            InlineTag::Sub => "sub",
+            InlineTag::Sup => "sup",
+            InlineTag::Ins => "ins",
+            InlineTag::Spoiler => "span",
+        }
+    }
+
+    fn delimiter(self) -> &'static str {
+        match self {
+            InlineTag::Mark => "==",
+            InlineTag::Sub => "~",
+            InlineTag::Sup => "^",
+            InlineTag::Ins => "++",
+            InlineTag::Spoiler => "||",
+        }
+    }
+
+    /// subscripts and superscripts can not contain spaces
+    fn allows_spaces(self) -> bool {
+        matches!(self, InlineTag::Mark | InlineTag::Ins | InlineTag::Spoiler)
+    }
+
+    fn enabled(self, extensions: InlineExtensions) -> bool {
+        match self {
+            InlineTag::Mark => extensions.mark,
+            InlineTag::Sub => extensions.subscript,
+            InlineTag::Sup => extensions.superscript,
+            InlineTag::Ins => extensions.insert,
+            InlineTag::Spoiler => extensions.spoiler,
+        }
+    }
+}
+
+/// whether `content` can be between the delimiters of `tag`
+fn valid_content(tag: InlineTag, content: &str) -> bool {
+    !content.is_empty()
+        && content.trim() == content
+        && (tag.allows_spaces() || !content.contains(char::is_whitespace))
+}
+
+/// the enabled syntax whose delimiter is at the start of `text`
+fn delimiter_at(text: &str, extensions: InlineExtensions) -> Option<InlineTag> {
+    InlineTag::ALL
+        .into_iter()
+        .filter(|tag| tag.enabled(extensions))
+        .find(|tag| {
+            let d = tag.delimiter();
+            // `~~` and `^^` are not single delimiters
+            text.starts_with(d) && !(d.len() == 1 && text[1..].starts_with(d))
+        })
+}
+
+/// a delimiter waiting for the one that closes it
+struct Opening {
+    tag: InlineTag,
+    range: Range<usize>,
+    /// the number of inline elements, like emphasis or links, around it
+    depth: usize,
+}
+
+/// the elements of the enabled syntaxes, as the ranges of their two delimiters.
+/// Both delimiters are in the same block, inside the same inline elements:
+/// `==**key** term==` is marked, `==a **b== c**` is not.
+fn find_elements(
+    pass: &Pass,
+    events: &[(Event, Range<usize>)],
+    extensions: InlineExtensions,
+) -> Vec<(InlineTag, Range<usize>, Range<usize>)> {
+    let source = pass.source;
+    let mut found = Vec::new();
+    let mut open: Vec<Opening> = Vec::new();
+    let mut depth = 0;
+    // inside code blocks and images, where an embed can not be
+    let mut skipped = 0;
+
+    // the delimiters are looked for in the consecutive text events
+    let mut scan = |run: Range<usize>, depth: usize, open: &mut Vec<Opening>| {
+        let mut i = run.start;
+        while i < run.end {
+            let escaped = source[..i].ends_with('\\');
+            let Some(tag) = delimiter_at(&source[i..run.end], extensions).filter(|_| !escaped)
+            else {
+                i += source[i..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            let len = tag.delimiter().len();
+            let closes = source[..i]
+                .chars()
+                .next_back()
+                .is_some_and(|c| !c.is_whitespace());
+            let opening = open.iter().rposition(|o| o.tag == tag && o.depth == depth);
+            match opening {
+                Some(k) if closes && valid_content(tag, &source[open[k].range.end..i]) => {
+                    found.push((tag, open[k].range.clone(), i..i + len));
+                    // the delimiters opened inside would cross this element
+                    open.truncate(k);
+                }
+                _ => {
+                    let opens = source[i + len..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| !c.is_whitespace());
+                    if opens {
+                        open.push(Opening {
+                            tag,
+                            range: i..i + len,
+                            depth,
+                        });
+                    }
+                }
+            }
+            i += len;
+        }
+    };
+
+    let mut run: Option<Range<usize>> = None;
+    for (event, range) in events {
+        if let (Event::Text(_), 0) = (event, skipped) {
+            run = Some(run.map_or(range.clone(), |run| run.start..range.end));
+            continue;
+        }
+        if let Some(run) = run.take() {
+            scan(run, depth, &mut open);
+        }
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::Image(..)) => skipped += 1,
+            Event::End(Tag::CodeBlock(_) | Tag::Image(..)) => skipped -= 1,
+            Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => {
+                depth += 1
+            }
+            Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => {
+                depth -= 1;
+                open.retain(|o| o.depth <= depth);
+            }
+            // a block starts or ends
+            Event::Start(_) | Event::End(_) => open.clear(),
+            _ => {}
+        }
+    }
+    if let Some(run) = run {
+        scan(run, depth, &mut open);
+    }
+    found
+}
+
+/// replace the delimiters of the enabled syntaxes by an embed around their content
+pub(crate) fn inline_extensions(pass: &mut Pass, extensions: InlineExtensions) {
+    if !extensions.any() {
+        return;
+    }
+    let source = pass.source;
+    let events = pass.events();
+
+    // with the strikethrough extension, `~2~` is already parsed
+    if extensions.subscript {
+        for (event, range) in &events {
+            let Event::Start(Tag::Strikethrough) = event else {
+                continue;
+            };
+            let text = &source[range.clone()];
+            if text.starts_with("~~") || text.len() < 2 {
+                continue;
+            }
+            if valid_content(InlineTag::Sub, &text[1..text.len() - 1]) {
+                embed(
+                    pass,
+                    InlineTag::Sub,
+                    range.start..range.start + 1,
+                    range.end - 1..range.end,
+                );
+            }
+        }
+    }
+
+    for (tag, open, close) in find_elements(pass, &events, extensions) {
+        embed(pass, tag, open, close);
+    }
+}
+
+fn embed(pass: &mut Pass, tag: InlineTag, open: Range<usize>, close: Range<usize>) {
+    let embed = Embed::Inline {
+        tag,
+        position: pass.original_range(open.start..close.end),
+    };
+    let open_tag = pass.open_tag(embed);
+    pass.replace(open, open_tag);
+    pass.replace(close, CLOSE_TAG);
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn prepare(src: &'static str) -> Prepared {
+        let inline_extensions = InlineExtensions::all();
+        Prepared::new(&yew::props!(Props { src, inline_extensions }))
+    }
+
+    #[wasm_bindgen_test]
+    fn the_content_is_markdown() {
+        let prepared = prepare("==**key** term== and ||see [x](y)||\n");
+        assert_eq!(
+            prepared.source,
+            "<YewMdEmbed id=\"0\">**key** term</YewMdEmbed> and <YewMdEmbed id=\"1\">see [x](y)</YewMdEmbed>\n"
+        );
+        assert!(matches!(
+            prepared.embeds[0],
+            Embed::Inline {
+                tag: InlineTag::Mark,
+                position: ref p,
+            } if *p == (0..16)
+        ));
+        assert!(matches!(
+            prepared.embeds[1],
+            Embed::Inline {
+                tag: InlineTag::Spoiler,
+                ..
+            }
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    fn subscripts_and_superscripts() {
+        let prepared = prepare("H~2~O and x^2^\n");
+        assert_eq!(
+            prepared.source,
+            "H<YewMdEmbed id=\"0\">2</YewMdEmbed>O and x<YewMdEmbed id=\"1\">2</YewMdEmbed>\n"
+        );
+        // a subscript can not contain spaces
+        assert_eq!(prepare("a ~b c~ d\n").embeds.len(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn the_delimiters_are_in_the_same_element() {
+        for src in ["==a **b== c**\n", "*a ==b* c==\n", "==a\n\nb==\n", "\\==a==\n", "a == b ==\n"] {
+            let prepared = prepare(src);
+            assert_eq!(prepared.source, src);
+            assert!(prepared.embeds.is_empty());
+        }
+    }
+}
@@ -6,19 +6,63 @@ use rust_web_markdown::{
 
 use core::ops::Range;
 
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 
 pub use rust_web_markdown::{
     LinkDescription, Options, ComponentCreationError
 };
 
 use yew::prelude::{
-    function_component, html, AttrValue, Callback, Html, Properties, UseStateHandle,
+    function_component, hook, html, use_effect_with, use_memo, use_mut_ref, AttrValue, Callback,
+    Html, Properties, UseStateHandle,
 };
 
 pub type MdComponentProps = rust_web_markdown::MdComponentProps<Html>;
 
-use web_sys::{window, MouseEvent};
+use web_sys::MouseEvent;
+
+mod abbreviations;
+mod autolinks;
+mod bibliography;
+mod callouts;
+mod citations;
+mod containers;
+mod deflists;
+mod embed;
+mod emoji;
+mod footnotes;
+mod glossary;
+mod highlight;
+mod inline;
+mod math;
+mod mentions;
+mod prepare;
+mod sections;
+mod source;
+mod spoilers;
+mod stylesheets;
+mod tabs;
+mod typography;
+mod variables;
+mod xref;
+
+pub use bibliography::{BibEntry, Bibliography, Person};
+pub use callouts::{Callout, CalloutKind};
+pub use highlight::CodeBlock;
+pub use inline::InlineExtensions;
+#[cfg(feature = "highlight")]
+pub use highlight::theme_stylesheet;
+pub use math::MathBlock;
+pub use mentions::{ResolvedReference, TextReference, TextReferenceKind};
+pub use sections::OutlineEntry;
+pub use stylesheets::{HighlightTheme, STYLESHEET};
+pub use typography::Typography;
+
+use embed::EMBED_TAG;
+use prepare::Prepared;
+use stylesheets::Stylesheet;
 
 
 #[derive(Clone, Debug)]
@@ -29,10 +73,22 @@ pub struct MarkdownMouseEvent {
     /// the corresponding range in the markdown source, as a slice of [`u8`][u8]
     pub position: Range<usize>,
 
+    /// the kind of the innermost callout around the element, like `note`
+    pub callout: Option<AttrValue>,
+
     // TODO: add a clonable tag for the type of the element
     // pub tag: pulldown_cmark::Tag<'a>,
 }
 
+/// a problem found in the markdown source, like a reference to a label that does not exist
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+
+    /// the corresponding range in the markdown source, as a slice of [`u8`][u8]
+    pub position: Range<usize>,
+}
+
 /// component store.
 /// It is called when therer is a `<CustomComponent>` inside the markdown source.
 /// It is basically a hashmap but more efficient for a small number of items
@@ -64,7 +120,70 @@ impl CustomComponents
 }
 
 
-impl<'a> Context<'a, 'static> for &'a Props {
+/// what `render_markdown` needs to render one `Markdown` component
+#[derive(Clone, Copy)]
+pub(crate) struct MdContext<'a> {
+    props: &'a Props,
+    prepared: &'a Prepared,
+    /// the stylesheets requested while rendering
+    stylesheets: &'a RefCell<Vec<Stylesheet>>,
+}
+
+impl<'a> MdContext<'a> {
+    /// a handler reporting clicks at `position`, in the source written by the user
+    fn handler(self, position: Range<usize>, stop_propagation: bool) -> Callback<MouseEvent> {
+        match &self.props.onclick {
+            Some(f) => {
+                let f = f.clone();
+                let callout = self.prepared.callout_at(&position);
+                Callback::from(move |e: MouseEvent| {
+                    if stop_propagation {
+                        e.stop_propagation()
+                    }
+                    let report = MarkdownMouseEvent {
+                        mouse_event: e,
+                        position: position.clone(),
+                        callout: callout.clone(),
+                    };
+                    f.emit(report)
+                    }
+                )
+            },
+            None => Callback::noop(),
+        }
+    }
+
+    /// mount [`STYLESHEET`] along with the component
+    fn require_stylesheet(self) {
+        let mut stylesheets = self.stylesheets.borrow_mut();
+        if !stylesheets.contains(&Stylesheet::Base) {
+            stylesheets.push(Stylesheet::Base)
+        }
+    }
+
+    /// mount a `<link>` along with the component, served from `asset_base` if it is set
+    fn require_link(self, rel: &str, href: &str, integrity: &str, crossorigin: &str) {
+        // the hash of the CDN's file would block a self-hosted copy that is not byte-identical
+        let (href, integrity) = match &self.props.asset_base {
+            Some(base) => (stylesheets::rebase(href, base), ""),
+            None => (href.to_string(), integrity),
+        };
+        let link = Stylesheet::Link {
+            rel: rel.to_string(),
+            href,
+            integrity: integrity.to_string(),
+            crossorigin: crossorigin.to_string(),
+        };
+
+        // mounted by the `Markdown` component once rendered
+        let mut stylesheets = self.stylesheets.borrow_mut();
+        if !stylesheets.contains(&link) {
+            stylesheets.push(link)
+        }
+    }
+}
+
+impl<'a> Context<'a, 'static> for MdContext<'a> {
     type View = Html;
 
     type Handler<T: 'static> = Callback<T>;
@@ -76,22 +195,21 @@ impl<'a> Context<'a, 'static> for &'a Props {
             theme,
             wikilinks,
             hard_line_breaks,
-            parse_options,
             ..
-        } = self;
+        } = self.props;
 
         MarkdownProps {
             theme: theme.as_deref(),
-            wikilinks: *wikilinks,
+            wikilinks: cfg!(feature = "wikilinks") && *wikilinks,
             hard_line_breaks: *hard_line_breaks,
-            parse_options: parse_options.as_ref(),
+            parse_options: Some(&self.prepared.options),
         }
     }
 
 
     #[cfg(feature="debug")]
     fn send_debug_info(self, info: Vec<String>) {
-        if let Some(sender) = &self.send_debug_info {
+        if let Some(sender) = &self.props.send_debug_info {
             sender.emit(info)
         }
     }
@@ -217,20 +335,13 @@ impl<'a> Context<'a, 'static> for &'a Props {
     }
 
     fn mount_dynamic_link(self, rel: &str, href: &str, integrity: &str, crossorigin: &str) {
-        let document = window().unwrap().document().unwrap();
-
-        let link = document
-            .create_element("link")
-            .unwrap();
-
-        link.set_attribute("rel", rel).unwrap();
-        link.set_attribute("href", href).unwrap();
-        link.set_attribute("integrity", integrity).unwrap();
-        link.set_attribute("crossorigin", crossorigin).unwrap();
-
-        document.head()
-            .unwrap()
-            .append_child(&link).unwrap();
+        // the stylesheet of the built-in math renderer is useless
+        // without maths, or when they are rendered by the `math_renderer`
+        let katex_unused = cfg!(not(feature = "math")) || self.props.math_renderer.is_some();
+        if katex_unused && href.contains("katex") {
+            return
+        }
+        self.require_link(rel, href, integrity, crossorigin)
     }
 
     fn el_input_checkbox(self, checked: bool, attributes: ElementAttributes<Callback<MouseEvent>>) -> Self::View {
@@ -251,46 +362,34 @@ impl<'a> Context<'a, 'static> for &'a Props {
     }
 
     fn make_md_handler(self, position: Range<usize>, stop_propagation: bool) -> Self::Handler<MouseEvent> {
-        match &self.onclick {
-            Some(f) => {
-                let f = f.clone();
-                Callback::from(move |e: MouseEvent| {
-                    if stop_propagation {
-                        e.stop_propagation()
-                    }
-                    let report = MarkdownMouseEvent {
-                        mouse_event: e,
-                        position: position.clone(),
-                    };
-                    f.emit(report)
-                    }
-                )
-            },
-            None => Callback::noop(),
-        }
+        self.handler(self.prepared.original_range(position), stop_propagation)
     }
 
     fn has_custom_links(self) -> bool {
-        self.render_links.is_some()
+        self.props.render_links.is_some()
     }
 
     fn render_links(self, link: LinkDescription<Html>) -> Result<Html, String> {
-        let f = self.render_links.clone().unwrap();
+        let f = self.props.render_links.clone().unwrap();
         Ok(f.emit(link))
     }
 
     fn set_frontmatter(self, frontmatter: String) {
-        if let Some(setter) = &self.frontmatter {
+        if let Some(setter) = &self.props.frontmatter {
             setter.set(frontmatter)
         }
     }
 
     fn has_custom_component(self, name: &str) -> bool {
-        self.components.0.get(name).is_some()
+        name == EMBED_TAG
+            || (cfg!(feature = "components") && self.props.components.0.get(name).is_some())
     }
 
     fn render_custom_component(self, name: &str, input: rust_web_markdown::MdComponentProps<Self::View>) -> Result<Self::View, ComponentCreationError> {
-        let f = self.components.0.get(name).unwrap();
+        if name == EMBED_TAG {
+            return self.render_embed(input)
+        }
+        let f = self.props.components.0.get(name).unwrap();
         f.emit(input)
     }
 
@@ -300,6 +399,13 @@ impl<'a> Context<'a, 'static> for &'a Props {
 pub struct Props {
     pub src: AttrValue,
 
+    /// the values of the `{{name}}` placeholders of `src`.
+    /// They are replaced everywhere, including in link targets and code blocks,
+    /// and the clicks on a value report the position of its placeholder.
+    /// In the text, a value is plain text: markdown in it is escaped.
+    #[prop_or_default]
+    pub variables: HashMap<String, AttrValue>,
+
     #[prop_or_default]
     pub onclick: Option<Callback<MarkdownMouseEvent, ()>>,
 
@@ -309,9 +415,137 @@ pub struct Props {
     #[prop_or_default]
     pub theme: Option<String>,
 
+    /// renders the code blocks instead of the built-in highlighter.
+    /// The produced html is wrapped in an element that reports clicks to `onclick`.
+    #[prop_or_default]
+    pub code_highlighter: Option<Callback<CodeBlock, Html>>,
+
+    /// highlight code blocks with css classes instead of inline styles,
+    /// see [`CodeBlock::highlighted_with_classes`].
+    /// The `theme` is ignored: the colors come from [`theme_stylesheet`].
+    #[prop_or(false)]
+    pub class_highlighting: bool,
+
+    /// renders the formulas instead of the built-in renderer.
+    /// The produced html is wrapped in an element that reports clicks to `onclick`.
+    ///
+    /// With the `mathml` feature, `Callback::from(|m: MathBlock| m.mathml())`
+    /// renders them as MathML, without any external stylesheet.
+    #[prop_or_default]
+    pub math_renderer: Option<Callback<MathBlock, Html>>,
+
+    /// the stylesheet to mount for `class_highlighting`.
+    /// Changing it only replaces the stylesheet, the markdown is not rendered again.
+    #[prop_or_default]
+    pub highlight_theme: Option<HighlightTheme>,
+
+    /// when `false`, no stylesheet is added to `<head>`:
+    /// the page has to include them itself.
+    /// The css for `highlight_theme` is given by [`theme_stylesheet`].
+    #[prop_or(true)]
+    pub dynamic_stylesheets: bool,
+
+    /// the nonce of the stylesheets added to `<head>`,
+    /// for a `Content-Security-Policy` with `style-src 'nonce-...'`
+    #[prop_or_default]
+    pub stylesheet_nonce: Option<AttrValue>,
+
+    /// where the external stylesheets are served from, instead of their CDN.
+    /// For example, with `asset_base="/assets/katex"`, the katex stylesheet
+    /// is loaded from `/assets/katex/katex.min.css`.
+    /// These stylesheets are loaded without the `integrity` hash of the CDN.
+    #[prop_or_default]
+    pub asset_base: Option<AttrValue>,
+
+    /// kinds of callouts, in addition to [`CalloutKind::builtin`].
+    /// A kind with the name of a built-in one replaces it.
+    #[prop_or_default]
+    pub callout_kinds: Vec<CalloutKind>,
+
+    /// renders the callouts instead of the built-in boxes.
+    /// The produced html is wrapped in an element that reports clicks to `onclick`.
+    #[prop_or_default]
+    pub callout_renderer: Option<Callback<Callout, Html>>,
+
+    /// let the reader fold each heading, to hide its section
+    #[prop_or(false)]
+    pub foldable_headings: bool,
+
+    /// number the headings from this level: with `Some(2)`, the `##` headings
+    /// are numbered 1, 2, 3..., the `###` headings 1.1, 1.2... and the `#` headings are not numbered.
+    /// The headings ending with `{-}` or `{.unnumbered}` are not numbered.
+    #[prop_or_default]
+    pub number_sections: Option<u8>,
+
+    /// choosing a tab of a `:::tabs` group chooses the tab with the same title
+    /// in all the groups of the page, and the choice is remembered for the next visits
+    #[prop_or(false)]
+    pub sync_tabs: bool,
+
+    /// turn the urls of the text, like `https://...` or `www....`, into links
+    #[prop_or(true)]
+    pub autolinks: bool,
+
+    /// the kinds of references looked for in the text, all of them by default
+    #[prop_or_else(TextReferenceKind::all)]
+    pub text_references: Vec<TextReferenceKind>,
+
+    /// turns the references found in the text, like `@alice` or `PROJ-123`, into links.
+    /// The references for which it returns `None` stay as they are.
+    /// The links are rendered by `render_links`, if it is set.
+    #[prop_or_default]
+    pub resolve_reference: Option<Callback<TextReference, Option<ResolvedReference>>>,
+
+    /// replace shortcodes like `:rocket:` by their emoji, outside of code and links
+    #[cfg(feature = "emoji")]
+    #[prop_or(false)]
+    pub emoji_shortcodes: bool,
+
+    /// images for shortcodes, by name: `ship-it` for `:ship-it:`.
+    /// They are replaced even if `emoji_shortcodes` is `false`,
+    /// or without the `emoji` feature.
+    #[prop_or_default]
+    pub custom_emoji: Option<Rc<HashMap<String, AttrValue>>>,
+
+    /// typographic replacements in the text, like curly quotes and dashes
+    #[prop_or_default]
+    pub typography: Option<Typography>,
+
+    /// terms to explain where they appear in the text, with their definition.
+    /// They are matched without case, outside of code, links and headings.
+    #[prop_or_default]
+    pub glossary: Option<Rc<HashMap<String, Html>>>,
+
+    /// explain every occurrence of the terms of the `glossary`, instead of only the first one
+    #[prop_or(false)]
+    pub glossary_all_occurrences: bool,
+
+    /// the works that can be cited with `[@key]`, like in Pandoc.
+    /// The works that are cited are listed at the end of the document.
+    #[prop_or_default]
+    pub bibliography: Option<Rc<Bibliography>>,
+
+    /// show the content of a footnote in a popover
+    /// when its reference is hovered or focused
+    #[prop_or(false)]
+    pub footnote_previews: bool,
+
     #[prop_or(false)]
     pub wikilinks: bool,
 
+    /// read a term followed by lines starting with `: ` as a definition list
+    #[prop_or(false)]
+    pub definition_lists: bool,
+
+    /// make a figure of an image alone on its line followed by other lines,
+    /// that are its caption. Without it, only the images labelled with `{#fig:...}` are figures
+    #[prop_or(false)]
+    pub figure_captions: bool,
+
+    /// `==marked==`, `H~2~O`, `x^2^`, `++inserted++` and `||spoiler||`, each enabled on its own
+    #[prop_or_default]
+    pub inline_extensions: InlineExtensions,
+
     #[prop_or(false)]
     pub hard_line_breaks: bool,
 
@@ -326,9 +560,179 @@ pub struct Props {
 
     #[prop_or_default]
     pub send_debug_info: Option<Callback<Vec<String>>>,
+
+    /// called for each problem found in the source, after it is rendered
+    #[prop_or_default]
+    pub on_diagnostic: Option<Callback<Diagnostic>>,
+
+    /// called with the headings of the document and their numbers, after it is rendered.
+    /// It can be used to build a table of contents.
+    #[prop_or_default]
+    pub on_outline: Option<Callback<Vec<OutlineEntry>>>,
+
+    /// when `true` (the default), the markdown is only rendered again
+    /// if the source or the options changed.
+    /// New callbacks do not trigger a new render, so they can be created at each render
+    /// of the parent: `onclick`, `render_links`, `code_highlighter`, `math_renderer`,
+    /// `callout_renderer`, `resolve_reference` and the `components` with the same names.
+    /// The rendered elements always call the latest `onclick`, but the content is only
+    /// rendered again with the latest renderers when something else changes.
+    #[prop_or(true)]
+    pub caching: bool,
+}
+
+/// the result of rendering a `Markdown` component, kept while the props do not change
+struct Rendered {
+    props: Props,
+    html: Html,
+    stylesheets: Vec<Stylesheet>,
+    diagnostics: Vec<Diagnostic>,
+    outline: Vec<OutlineEntry>,
+}
+
+fn render(props: Props) -> Rendered {
+    let prepared = Prepared::new(&props);
+    let stylesheets = RefCell::new(Vec::new());
+    let cx = MdContext {
+        props: &props,
+        prepared: &prepared,
+        stylesheets: &stylesheets,
+    };
+    let html = render_markdown(cx, &prepared.source);
+    Rendered {
+        html,
+        stylesheets: stylesheets.into_inner(),
+        diagnostics: prepared.diagnostics,
+        outline: prepared.outline,
+        props,
+    }
+}
+
+/// a callback that is set when `f` is, that never changes, and that calls the latest `f`
+#[hook]
+fn use_stable_callback<IN: 'static, OUT: Default + 'static>(
+    f: &Option<Callback<IN, OUT>>,
+) -> Option<Callback<IN, OUT>> {
+    let latest = use_mut_ref(|| None::<Callback<IN, OUT>>);
+    *latest.borrow_mut() = f.clone();
+    let stable = use_memo((), move |_| {
+        Callback::from(move |input: IN| match &*latest.borrow() {
+            Some(f) => f.emit(input),
+            None => OUT::default(),
+        })
+    });
+    f.as_ref().map(|_| (*stable).clone())
+}
+
+/// components with the same names as `components`, that only change with the names,
+/// and that call the latest ones
+#[hook]
+fn use_stable_components(components: &CustomComponents) -> CustomComponents {
+    let latest = use_mut_ref(CustomComponents::new);
+    *latest.borrow_mut() = components.clone();
+    let names: Vec<&'static str> = components.0.keys().copied().collect();
+    let stable = use_memo(names, move |names| {
+        let forward = |name: &'static str| {
+            let latest = latest.clone();
+            Callback::from(move |input: MdComponentProps| match latest.borrow().0.get(name) {
+                Some(f) => f.emit(input),
+                None => Ok(html! {}),
+            })
+        };
+        CustomComponents(names.iter().map(|&name| (name, forward(name))).collect())
+    });
+    (*stable).clone()
 }
 
 #[function_component]
 pub fn Markdown(props: &Props) -> Html {
-    render_markdown(props, &props.src)
+    let theme = props.highlight_theme.clone();
+    let nonce = props.stylesheet_nonce.clone();
+    let dynamic_stylesheets = props.dynamic_stylesheets;
+    let on_diagnostic = props.on_diagnostic.clone();
+    let on_outline = props.on_outline.clone();
+
+    // callbacks that never change, so that they do not invalidate the cache
+    let onclick = use_stable_callback(&props.onclick);
+    let render_links = use_stable_callback(&props.render_links);
+    let code_highlighter = use_stable_callback(&props.code_highlighter);
+    let math_renderer = use_stable_callback(&props.math_renderer);
+    let callout_renderer = use_stable_callback(&props.callout_renderer);
+    let resolve_reference = use_stable_callback(&props.resolve_reference);
+    let components = use_stable_components(&props.components);
+
+    // to render, only whether `on_outline` is set matters
+    let outline_placeholder = use_memo((), |_| Callback::from(|_: Vec<OutlineEntry>| ()));
+
+    let props = Props {
+        onclick,
+        render_links,
+        code_highlighter,
+        math_renderer,
+        callout_renderer,
+        resolve_reference,
+        components,
+        on_outline: props.on_outline.as_ref().map(|_| (*outline_placeholder).clone()),
+        // not needed to render
+        highlight_theme: None,
+        stylesheet_nonce: None,
+        dynamic_stylesheets: true,
+        on_diagnostic: None,
+        ..props.clone()
+    };
+
+    let cache = use_mut_ref(|| None::<Rendered>);
+    let (html, mut stylesheets, diagnostics, outline) = {
+        let mut cache = cache.borrow_mut();
+        let up_to_date = matches!(&*cache, Some(r) if props.caching && r.props == props);
+        if !up_to_date {
+            *cache = Some(render(props));
+        }
+        let rendered = cache.as_ref().unwrap();
+        (
+            rendered.html.clone(),
+            rendered.stylesheets.clone(),
+            rendered.diagnostics.clone(),
+            rendered.outline.clone(),
+        )
+    };
+
+    if let Some(theme) = &theme {
+        stylesheets.push(Stylesheet::HighlightTheme(theme.clone()));
+    }
+    if !dynamic_stylesheets {
+        stylesheets.clear();
+    }
+
+    // mounted once the component is rendered, removed when it is unmounted.
+    // The new ones are acquired before the old ones are released,
+    // so the stylesheets used before and after a change stay mounted.
+    let mounted = use_mut_ref(Vec::<Stylesheet>::new);
+    {
+        let mounted = mounted.clone();
+        use_effect_with((stylesheets, nonce), move |(sheets, nonce)| {
+            for sheet in sheets {
+                stylesheets::acquire(sheet, nonce.as_deref());
+            }
+            let old = std::mem::replace(&mut *mounted.borrow_mut(), sheets.clone());
+            old.iter().for_each(stylesheets::release);
+        });
+    }
+    use_effect_with((), move |_| {
+        move || mounted.borrow().iter().for_each(stylesheets::release)
+    });
+
+    use_effect_with(diagnostics, move |diagnostics| {
+        if let Some(f) = on_diagnostic {
+            diagnostics.iter().cloned().for_each(|d| f.emit(d))
+        }
+    });
+
+    use_effect_with(outline, move |outline| {
+        if let Some(f) = on_outline {
+            f.emit(outline.clone())
+        }
+    });
+
+    html
 }
@@ -0,0 +1,63 @@
+//! formulas rendered outside of `rust-web-markdown`
+
+use core::ops::Range;
+
+use yew::prelude::{html, Html};
+
+/// a `$...$` or `$$...$$` formula, as given to the `math_renderer` of [`Props`](crate::Props).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MathBlock {
+    /// the TeX source, without the dollars
+    pub tex: String,
+
+    /// `true` for `$$...$$`, `false` for `$...$`
+    pub display: bool,
+
+    /// the range of the formula in the markdown source, dollars included
+    pub position: Range<usize>,
+}
+
+impl MathBlock {
+    /// render the formula with katex, like the built-in renderer.
+    /// It needs the katex stylesheet: the `Markdown` component only mounts it
+    /// when there is no `math_renderer`, so a `math_renderer` calling this
+    /// needs the page to include it.
+    #[cfg(feature = "math")]
+    pub fn katex(&self) -> Html {
+        let opts = katex::Opts::builder()
+            .display_mode(self.display)
+            .build()
+            .unwrap();
+        match katex::render_with_opts(&self.tex, &opts) {
+            Ok(html) => Html::from_html_unchecked(html.into()),
+            Err(e) => self.error(&e.to_string()),
+        }
+    }
+
+    /// render the formula as MathML, that browsers display without any stylesheet.
+    /// If the TeX is invalid, the source is shown along with the error.
+    #[cfg(feature = "mathml")]
+    pub fn mathml(&self) -> Html {
+        use latex2mathml::{latex_to_mathml, DisplayStyle};
+
+        let style = if self.display {
+            DisplayStyle::Block
+        } else {
+            DisplayStyle::Inline
+        };
+        match latex_to_mathml(&self.tex, style) {
+            Ok(mathml) => Html::from_html_unchecked(mathml.into()),
+            Err(e) => self.error(&e.to_string()),
+        }
+    }
+
+    /// the TeX source along with a message explaining why it could not be rendered
+    pub fn error(&self, message: &str) -> Html {
+        html! {
+            <span class="md-math-error" title={message.to_string()}>
+                <code>{self.tex.clone()}</code>
+                <small>{message.to_string()}</small>
+            </span>
+        }
+    }
+}
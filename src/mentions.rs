@@ -0,0 +1,158 @@
+//! mentions, hashtags and issues written in the text, like `@alice`, `#release`,
+//! `PROJ-123` or `org/repo#45`, turned into links by the `resolve_reference` of [`Props`](crate::Props).
+//!
+//! The links are written in the source as markdown links,
+//! so that they are rendered by `render_links` like the others.
+
+use pulldown_cmark::Tag;
+use yew::prelude::Callback;
+
+use crate::abbreviations::is_word_char;
+use crate::prepare::Pass;
+use crate::source::Piece;
+
+/// a kind of reference that can be written in the text
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextReferenceKind {
+    /// `@alice`
+    Mention,
+    /// `#release`
+    Hashtag,
+    /// `PROJ-123`
+    Issue,
+    /// `org/repo#45`
+    RepositoryIssue,
+}
+
+impl TextReferenceKind {
+    pub fn all() -> Vec<Self> {
+        vec![
+            TextReferenceKind::Mention,
+            TextReferenceKind::Hashtag,
+            TextReferenceKind::Issue,
+            TextReferenceKind::RepositoryIssue,
+        ]
+    }
+
+    /// the length of the reference of this kind at the start of `text`
+    fn parse(self, text: &str) -> Option<usize> {
+        let name_len = |s: &str| s.find(|c: char| !(is_word_char(c) || c == '-')).unwrap_or(s.len());
+        let digits_len = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let len = match self {
+            TextReferenceKind::Mention => {
+                let rest = text.strip_prefix('@')?;
+                let name = rest[..name_len(rest)].trim_end_matches('-');
+                (!name.is_empty()).then_some(1 + name.len())?
+            }
+            TextReferenceKind::Hashtag => {
+                let rest = text.strip_prefix('#')?;
+                if !rest.starts_with(char::is_alphabetic) {
+                    return None;
+                }
+                1 + rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len())
+            }
+            TextReferenceKind::Issue => {
+                let key = text.find(|c: char| !(c.is_ascii_uppercase() || c.is_ascii_digit()))?;
+                if key < 2 || !text.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    return None;
+                }
+                let rest = text[key..].strip_prefix('-')?;
+                let number = digits_len(rest);
+                (number > 0).then_some(key + 1 + number)?
+            }
+            TextReferenceKind::RepositoryIssue => {
+                let segment_len = |s: &str| s.find(|c: char| !(is_word_char(c) || c == '-' || c == '.')).unwrap_or(s.len());
+                let org = segment_len(text);
+                let rest = text[org..].strip_prefix('/')?;
+                let repo = segment_len(rest);
+                let number = digits_len(rest[repo..].strip_prefix('#')?);
+                if org == 0 || repo == 0 || number == 0 {
+                    return None;
+                }
+                org + 1 + repo + 1 + number
+            }
+        };
+        // the reference must end with a word
+        (!text[len..].starts_with(is_word_char)).then_some(len)
+    }
+}
+
+/// a reference found in the text, as given to the `resolve_reference` of [`Props`](crate::Props).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextReference {
+    pub kind: TextReferenceKind,
+    /// the reference as written, like `@alice`
+    pub text: String,
+}
+
+/// the link for a [`TextReference`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedReference {
+    pub href: String,
+    /// the text of the link
+    pub label: String,
+    /// shown when the link is hovered
+    pub title: Option<String>,
+}
+
+/// `text` with the characters that have a meaning in markdown escaped
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// replace the references that `resolve` knows by markdown links
+pub(crate) fn mentions(
+    pass: &mut Pass,
+    kinds: &[TextReferenceKind],
+    resolve: &Callback<TextReference, Option<ResolvedReference>>,
+) {
+    // `org/repo#45` before `#45`
+    let mut kinds = kinds.to_vec();
+    kinds.sort_by_key(|k| *k != TextReferenceKind::RepositoryIssue);
+
+    let source = pass.source;
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..)));
+    let found = pass.scan(&runs, |before, text| {
+        if before.ends_with(|c: char| is_word_char(c) || c == '/') {
+            return None;
+        }
+        kinds.iter().find_map(|&kind| Some((kind.parse(text)?, kind)))
+    });
+    for (range, kind) in found {
+        let reference = TextReference {
+            kind,
+            text: source[range.clone()].to_string(),
+        };
+        let Some(link) = resolve.emit(reference.clone()) else {
+            continue;
+        };
+        // copied when possible, to keep the positions of its characters
+        let copy = link.label == reference.text
+            && !link.label.contains(['_', '*', '\\', '[', ']', '<', '>', '`']);
+        let label = match copy {
+            true => Piece::Copy(range.clone()),
+            false => Piece::Text(escape(&link.label)),
+        };
+        let title = match &link.title {
+            Some(title) => format!(" \"{}\"", title.replace('\\', "\\\\").replace('"', "\\\"")),
+            None => String::new(),
+        };
+        let destination = link.href.replace('<', "%3C").replace('>', "%3E");
+        pass.replace_with(
+            range,
+            vec![
+                Piece::Text("[".to_string()),
+                label,
+                Piece::Text(format!("](<{destination}>{title})")),
+            ],
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! preparation of the source before it is given to `render_markdown`.
+//!
+//! Some constructs are rendered by yew-markdown itself instead of `rust-web-markdown`.
+//! Each pass parses the current source, and replaces these constructs by a
+//! `<YewMdEmbed id="..."/>` custom component pointing to an [`Embed`].
+
+use core::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+
+use rust_web_markdown::Options;
+
+use yew::prelude::AttrValue;
+
+use crate::abbreviations::abbreviations;
+use crate::autolinks::autolinks;
+use crate::callouts;
+use crate::citations::citations;
+use crate::containers::containers;
+use crate::deflists::definition_lists;
+use crate::embed::{Embed, EMBED_TAG};
+use crate::emoji::emoji;
+use crate::footnotes::{self, Footnotes};
+use crate::glossary::glossary;
+use crate::highlight::CodeBlock;
+use crate::inline::inline_extensions;
+use crate::math::MathBlock;
+use crate::mentions::mentions;
+use crate::sections::{self, OutlineEntry};
+use crate::source::{Piece, Rewriter, SourceMap};
+use crate::typography::typography;
+use crate::variables::variables;
+use crate::xref::{self, Targets};
+use crate::{Diagnostic, Props};
+
+/// the source that is really rendered, and what is needed to render the embeds.
+pub(crate) struct Prepared {
+    pub source: String,
+    pub options: Options,
+    pub embeds: Vec<Embed>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// the original position of the callouts, and their kind
+    pub callouts: Vec<(Range<usize>, AttrValue)>,
+    /// the headings of the document
+    pub outline: Vec<OutlineEntry>,
+    /// one map per pass, in the order they were applied
+    maps: Vec<SourceMap>,
+}
+
+impl Prepared {
+    pub fn new(props: &Props) -> Self {
+        #[allow(unused_mut)]
+        let mut options = props.parse_options.unwrap_or(Options::all());
+        // without katex, only the `math_renderer` can render the formulas
+        #[cfg(not(feature = "math"))]
+        if props.math_renderer.is_none() {
+            options.remove(Options::ENABLE_MATH);
+        }
+
+        let mut prepared = Self {
+            source: props.src.to_string(),
+            options,
+            embeds: Vec::new(),
+            diagnostics: Vec::new(),
+            callouts: Vec::new(),
+            outline: Vec::new(),
+            maps: Vec::new(),
+        };
+
+        // each pass parses the whole source: it only runs if its syntax can be there
+        // first, so that the values are parsed like the rest of the source
+        if !props.variables.is_empty() && prepared.has("{{") {
+            prepared.pass(|p| variables(p, &props.variables));
+        }
+
+        // the embeds are only made by the passes, not written in the source
+        if prepared.has(EMBED_TAG) {
+            prepared.pass(escape_embed_tags);
+        }
+
+        // one pass per level of nested callouts
+        let mut found = Vec::new();
+        let mut more = true;
+        while more && prepared.has("[!") {
+            prepared.pass(|p| more = callouts::callouts(p, &props.callout_kinds, &mut found));
+        }
+        prepared.callouts = found;
+        if prepared.has(":::") || prepared.has("<details") {
+            prepared.pass(containers);
+        }
+        if props.definition_lists && prepared.has(":") {
+            prepared.pass(definition_lists);
+        }
+
+        // before the passes that replace words by embeds: the outline keeps the text of the headings
+        if props.number_sections.is_some() || props.on_outline.is_some() {
+            let mut outline = Vec::new();
+            prepared.pass(|p| sections::outline(p, props.number_sections, &mut outline));
+            prepared.outline = outline;
+        }
+
+        // without the highlighter, the code blocks are rendered as plain `<pre><code>`
+        if cfg!(not(feature = "highlight")) || props.code_highlighter.is_some() || props.class_highlighting {
+            prepared.pass(code_blocks);
+        }
+
+        let mut targets = Targets::default();
+        if options.contains(Options::ENABLE_MATH) && prepared.has("\\label") {
+            prepared.pass(|p| xref::equations(p, &mut targets));
+        }
+        if prepared.has("![") || prepared.has("|") {
+            prepared.pass(|p| xref::figures(p, &mut targets, props.figure_captions));
+        }
+        if prepared.has("[@") || prepared.has("\\eqref{") {
+            prepared.pass(|p| xref::references(p, &targets));
+        }
+        if let Some(bibliography) = props.bibliography.as_ref().filter(|_| prepared.has("@")) {
+            prepared.pass(|p| citations(p, bibliography));
+        }
+        if prepared.has("*[") {
+            prepared.pass(abbreviations);
+        }
+        if let Some(terms) = &props.glossary {
+            prepared.pass(|p| glossary(p, terms, props.glossary_all_occurrences));
+        }
+        if props.inline_extensions.delimiters().any(|d| prepared.has(d)) {
+            prepared.pass(|p| inline_extensions(p, props.inline_extensions));
+        }
+        if props.autolinks && (prepared.has("://") || prepared.has("www.")) {
+            prepared.pass(autolinks);
+        }
+        if let Some(resolve) = &props.resolve_reference {
+            prepared.pass(|p| mentions(p, &props.text_references, resolve));
+        }
+        #[cfg(feature = "emoji")]
+        let shortcodes = props.emoji_shortcodes;
+        #[cfg(not(feature = "emoji"))]
+        let shortcodes = false;
+        if (shortcodes || props.custom_emoji.is_some()) && prepared.has(":") {
+            prepared.pass(|p| emoji(p, shortcodes, props.custom_emoji.as_ref()));
+        }
+        if let Some(t) = &props.typography {
+            prepared.pass(|p| typography(p, t));
+        }
+
+        if props.foldable_headings {
+            prepared.pass(sections::foldable_headings);
+        }
+
+        if options.contains(Options::ENABLE_FOOTNOTES) && prepared.has("[^") {
+            let mut footnotes = Footnotes::default();
+            prepared.pass(|p| footnotes::definitions(p, &mut footnotes));
+            prepared.pass(|p| footnotes::references(p, &footnotes));
+        }
+
+        if props.math_renderer.is_some() && prepared.has("$") {
+            prepared.pass(maths);
+        }
+
+        prepared
+    }
+
+    /// the range in the source written by the user that corresponds to `range`
+    /// in the prepared source.
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        original_range(&self.maps, range)
+    }
+
+    /// the kind of the innermost callout around `position`, in the source written by the user
+    pub fn callout_at(&self, position: &Range<usize>) -> Option<AttrValue> {
+        self.callouts
+            .iter()
+            .filter(|(range, _)| range.start <= position.start && position.end <= range.end)
+            .min_by_key(|(range, _)| range.len())
+            .map(|(_, kind)| kind.clone())
+    }
+
+    /// whether the current source contains `pattern`
+    fn has(&self, pattern: &str) -> bool {
+        self.source.contains(pattern)
+    }
+
+    fn pass(&mut self, f: impl FnOnce(&mut Pass)) {
+        let source = std::mem::take(&mut self.source);
+        let rewritten = {
+            let mut pass = Pass {
+                source: &source,
+                options: self.options,
+                rewriter: Rewriter::new(&source),
+                embeds: &mut self.embeds,
+                diagnostics: &mut self.diagnostics,
+                maps: &self.maps,
+            };
+            f(&mut pass);
+            (!pass.rewriter.is_empty()).then(|| pass.rewriter.finish())
+        };
+        match rewritten {
+            Some((rewritten, map)) => {
+                self.source = rewritten;
+                self.maps.push(map);
+            }
+            // nothing to copy, and no map to go through
+            None => self.source = source,
+        }
+    }
+}
+
+fn original_range(maps: &[SourceMap], range: Range<usize>) -> Range<usize> {
+    maps.iter()
+        .rev()
+        .fold(range, |range, map| map.original_range(range))
+}
+
+/// one rewrite of the source.
+pub(crate) struct Pass<'p> {
+    pub source: &'p str,
+    pub options: Options,
+    rewriter: Rewriter<'p>,
+    embeds: &'p mut Vec<Embed>,
+    diagnostics: &'p mut Vec<Diagnostic>,
+    maps: &'p [SourceMap],
+}
+
+impl<'p> Pass<'p> {
+    /// the events of the current source, with their positions
+    pub fn events(&self) -> Vec<(Event<'p>, Range<usize>)> {
+        Parser::new_ext(self.source, self.options)
+            .into_offset_iter()
+            .collect()
+    }
+
+    /// the ranges of text outside of the elements for which `skip` is true,
+    /// and outside of code blocks and images: an embed can not be in an alternative text.
+    /// Consecutive text events are merged: `[`, for example, is an event on its own.
+    pub fn text_runs(
+        &self,
+        events: &[(Event<'p>, Range<usize>)],
+        skip: impl Fn(&Tag) -> bool,
+    ) -> Vec<Range<usize>> {
+        let skip = |tag: &Tag| matches!(tag, Tag::CodeBlock(_) | Tag::Image(..)) || skip(tag);
+        let mut depth = 0;
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for (event, range) in events {
+            match event {
+                Event::Start(tag) if skip(tag) => depth += 1,
+                Event::End(tag) if skip(tag) => depth -= 1,
+                Event::Text(_) if depth == 0 => match runs.last_mut() {
+                    Some(last) if last.end == range.start => last.end = range.end,
+                    _ => runs.push(range.clone()),
+                },
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    /// the matches of `find` in `runs`, that do not overlap.
+    /// `find` is called at each character of the runs that is not escaped with `\`,
+    /// with the source before it and the text of the run from it,
+    /// and returns the length of the match.
+    pub fn scan<T>(
+        &self,
+        runs: &[Range<usize>],
+        mut find: impl FnMut(&'p str, &'p str) -> Option<(usize, T)>,
+    ) -> Vec<(Range<usize>, T)> {
+        let mut found = Vec::new();
+        for run in runs {
+            let mut i = run.start;
+            while i < run.end {
+                let before = &self.source[..i];
+                let matched = match before.ends_with('\\') {
+                    true => None,
+                    false => find(before, &self.source[i..run.end]),
+                };
+                match matched {
+                    Some((len, m)) if len > 0 => {
+                        found.push((i..i + len, m));
+                        i += len;
+                    }
+                    _ => i += self.source[i..].chars().next().map_or(1, char::len_utf8),
+                }
+            }
+        }
+        found
+    }
+
+    /// the range in the source written by the user that corresponds to `range`
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        original_range(self.maps, range)
+    }
+
+    /// report a problem at `range`
+    pub fn diagnostic(&mut self, range: Range<usize>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            message: message.into(),
+            position: self.original_range(range),
+        })
+    }
+
+    pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) {
+        self.rewriter.replace(range, text)
+    }
+
+    pub fn replace_with(&mut self, range: Range<usize>, pieces: Vec<Piece>) {
+        self.rewriter.replace_with(range, pieces)
+    }
+
+    /// replace the block at `range` by `embed`
+    pub fn embed_block(&mut self, range: Range<usize>, embed: Embed) {
+        let tag = self.tag(embed);
+        let mut text = self.block_lines(range.start, &[tag]);
+        // the lines after the block already have their prefix
+        text.truncate(text.rfind('\n').map_or(0, |i| i + 1));
+        self.rewriter.replace(range, text)
+    }
+
+    /// replace the inline element at `range` by `embed`
+    pub fn embed_inline(&mut self, range: Range<usize>, embed: Embed) {
+        let tag = self.tag(embed);
+        self.rewriter.replace(range, tag)
+    }
+
+    /// a self-closing tag for `embed`
+    pub fn tag(&mut self, embed: Embed) -> String {
+        format!("<{EMBED_TAG} id=\"{}\"/>", self.push_embed(embed))
+    }
+
+    /// a tag for `embed` that has children, closed by [`CLOSE_TAG`](crate::embed::CLOSE_TAG)
+    pub fn open_tag(&mut self, embed: Embed) -> String {
+        format!("<{EMBED_TAG} id=\"{}\">", self.push_embed(embed))
+    }
+
+    /// `tags`, each on its own line, after a blank line and followed by one.
+    /// The lines keep the block quotes and list items of the block starting at `block_start`.
+    ///
+    /// They must be inserted at the start or at the end of the content of a line of this block:
+    /// the text after them is then on a line of its own.
+    pub fn block_lines(&self, block_start: usize, tags: &[impl AsRef<str>]) -> String {
+        let line_start = self.source[..block_start].rfind('\n').map_or(0, |i| i + 1);
+        // the list markers are replaced by spaces, to continue the list item
+        let prefix: String = self.source[line_start..block_start]
+            .chars()
+            .map(|c| if c == '>' { '>' } else { ' ' })
+            .collect();
+
+        // the blank line ends a paragraph that the tags are inserted after
+        let mut text = format!("\n{prefix}\n");
+        for tag in tags {
+            text += &format!("{prefix}{}\n{prefix}\n", tag.as_ref());
+        }
+        text + &prefix
+    }
+
+    fn push_embed(&mut self, embed: Embed) -> usize {
+        self.embeds.push(embed);
+        self.embeds.len() - 1
+    }
+}
+
+/// `line` without up to 4 spaces of indentation
+pub(crate) fn strip_indent(line: &str) -> &str {
+    if let Some(rest) = line.strip_prefix('\t') {
+        return rest;
+    }
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(4)..]
+}
+
+/// escape the `<` of the embed tags written in the html of the source,
+/// so that they are not rendered as embeds
+fn escape_embed_tags(pass: &mut Pass) {
+    let source = pass.source;
+    for (event, range) in pass.events() {
+        let Event::Html(_) = event else {
+            continue;
+        };
+        let html = &source[range.clone()];
+        for (i, _) in html.match_indices(EMBED_TAG) {
+            let before = html[..i].strip_suffix('/').unwrap_or(&html[..i]);
+            let Some(before) = before.strip_suffix('<') else {
+                continue;
+            };
+            let lt = range.start + before.len();
+            pass.replace(lt..lt + 1, "&lt;");
+            pass.diagnostic(range.clone(), format!("`{EMBED_TAG}` is reserved for yew-markdown"));
+        }
+    }
+}
+
+/// take the code blocks out of the source,
+/// so that they are not highlighted by `rust-web-markdown`
+fn code_blocks(pass: &mut Pass) {
+    let events = pass.events();
+    for (i, (event, range)) in events.iter().enumerate() {
+        if let Event::Start(Tag::CodeBlock(kind)) = event {
+            let code: String = events[i + 1..]
+                .iter()
+                .map_while(|(e, _)| match e {
+                    Event::Text(t) => Some(t.as_ref()),
+                    _ => None,
+                })
+                .collect();
+            let language = match kind {
+                CodeBlockKind::Fenced(info) => info.split_whitespace().next(),
+                CodeBlockKind::Indented => None,
+            };
+            let block = CodeBlock {
+                language: language.map(str::to_string),
+                code,
+                position: pass.original_range(range.clone()),
+            };
+            pass.embed_block(range.clone(), Embed::Code(block));
+        }
+    }
+}
+
+/// take the formulas out of the source,
+/// so that they are not rendered by `rust-web-markdown`
+fn maths(pass: &mut Pass) {
+    for (event, range) in pass.events() {
+        let (tex, display) = match event {
+            Event::InlineMath(tex) => (tex, false),
+            Event::DisplayMath(tex) => (tex, true),
+            _ => continue,
+        };
+        let block = MathBlock {
+            tex: tex.to_string(),
+            display,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, Embed::Math(block));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn prepare(src: &'static str) -> Prepared {
+        Prepared::new(&yew::props!(Props { src }))
+    }
+
+    #[cfg(feature = "math")]
+    #[wasm_bindgen_test]
+    fn an_eqref_alone_is_resolved() {
+        let prepared = prepare("$$x \\label{eq:x}$$\n\nsee \\eqref{eq:x}\n");
+        assert!(prepared.diagnostics.is_empty());
+        assert!(prepared
+            .embeds
+            .iter()
+            .any(|e| matches!(e, Embed::Reference { text, .. } if text == "(1)")));
+    }
+
+    #[wasm_bindgen_test]
+    fn an_unresolved_eqref_is_reported() {
+        let prepared = prepare("see \\eqref{eq:typo}\n");
+        assert_eq!(prepared.diagnostics.len(), 1);
+        assert_eq!(prepared.diagnostics[0].position, 4..19);
+    }
+
+    #[wasm_bindgen_test]
+    fn embed_tags_in_the_source_are_escaped() {
+        let prepared = prepare("a <YewMdEmbed id=\"0\"/> b\n\n<YewMdEmbed id=\"0\">\n\n`<YewMdEmbed/>`\n");
+        assert_eq!(
+            prepared.source,
+            "a &lt;YewMdEmbed id=\"0\"/> b\n\n&lt;YewMdEmbed id=\"0\">\n\n`<YewMdEmbed/>`\n"
+        );
+        assert_eq!(prepared.diagnostics.len(), 2);
+        assert!(prepared.embeds.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn a_source_without_syntax_is_not_copied() {
+        let prepared = prepare("plain *text*\n");
+        assert_eq!(prepared.source, "plain *text*\n");
+        assert!(prepared.maps.is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+//! the sections of a document: the content after a heading,
+//! up to the next heading of the same level or above.
+
+use core::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::embed::{Embed, CLOSE_TAG, EMBED_TAG};
+use crate::prepare::Pass;
+
+/// a heading of the document, as given to the `on_outline` of [`Props`](crate::Props)
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineEntry {
+    /// from 1 for `#` to 6 for `######`
+    pub level: u8,
+    /// like `1.2.3`, if the headings are numbered and this one is not unnumbered
+    pub number: Option<String>,
+    /// the text of the heading, without its formatting
+    pub text: String,
+    /// the position of the heading in the markdown source
+    pub position: Range<usize>,
+}
+
+/// the range of the `{-}` or `{.unnumbered}` at the end of the first line of `heading`,
+/// and what must replace it: the other attributes, like `{#id}`
+fn unnumbered_marker(heading: &str) -> Option<(Range<usize>, String)> {
+    let line = heading.lines().next()?.trim_end();
+    let open = line.rfind('{')?;
+    let inside = line[open..].strip_prefix('{')?.strip_suffix('}')?;
+    let (markers, others): (Vec<&str>, Vec<&str>) = inside
+        .split_whitespace()
+        .partition(|a| *a == "-" || *a == ".unnumbered");
+    if markers.is_empty() {
+        return None;
+    }
+    Some(match others.is_empty() {
+        true => (line[..open].trim_end().len()..line.len(), String::new()),
+        false => (open..line.len(), format!("{{{}}}", others.join(" "))),
+    })
+}
+
+/// list the headings in `outline`, and number them from the level `numbering`
+/// if it is set. The headings marked `{-}` or `{.unnumbered}` are not numbered.
+pub(crate) fn outline(pass: &mut Pass, numbering: Option<u8>, outline: &mut Vec<OutlineEntry>) {
+    let source = pass.source;
+    let numbering = numbering.map(|start| start.max(1));
+    let mut counters = [0usize; 6];
+    // the heading being read, and the position of its marker
+    let mut current: Option<(OutlineEntry, Option<Range<usize>>)> = None;
+    // the number to insert before the content of the current heading
+    let mut pending = None;
+
+    for (event, range) in pass.events() {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                let level = level as u8;
+                let marker = unnumbered_marker(&source[range.clone()]).map(|(marker, with)| {
+                    let marker = range.start + marker.start..range.start + marker.end;
+                    pass.replace(marker.clone(), with);
+                    marker
+                });
+                let number = numbering.filter(|&start| level >= start && marker.is_none()).map(|start| {
+                    let i = (level - start) as usize;
+                    counters[i] += 1;
+                    counters[i + 1..].fill(0);
+                    counters[..=i].iter().map(|n| n.to_string()).collect::<Vec<_>>().join(".")
+                });
+                pending = number.clone();
+                let entry = OutlineEntry {
+                    level,
+                    number,
+                    text: String::new(),
+                    position: pass.original_range(range),
+                };
+                current = Some((entry, marker));
+            }
+            Event::End(Tag::Heading(..)) => {
+                if let Some((mut entry, _)) = current.take() {
+                    entry.text = entry.text.trim().to_string();
+                    outline.push(entry);
+                }
+            }
+            _ if current.is_some() => {
+                if let Some(number) = pending.take() {
+                    let tag = pass.tag(Embed::SectionNumber { number });
+                    pass.replace(range.start..range.start, tag);
+                }
+                if let (Event::Text(text) | Event::Code(text), Some((entry, marker))) = (&event, &mut current) {
+                    if marker.as_ref().map_or(true, |m| range.end <= m.start) {
+                        entry.text += text;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// whether `html` opens an embed that has children
+fn opens_embed(html: &str) -> bool {
+    html.starts_with(&format!("<{EMBED_TAG} ")) && !html.ends_with("/>")
+}
+
+/// put each section in a fold, with its heading as the summary.
+/// A section also ends with the embed it is in, like a callout.
+pub(crate) fn foldable_headings(pass: &mut Pass) {
+    let source = pass.source;
+    // the number of embeds around the current position
+    let mut depth = 0;
+    // the level of the open sections, and the depth of their heading
+    let mut open: Vec<(usize, usize)> = Vec::new();
+
+    for (event, range) in pass.events() {
+        let level = match event {
+            Event::Html(html) if opens_embed(html.trim()) => {
+                depth += 1;
+                continue;
+            }
+            Event::Html(html) if html.trim() == CLOSE_TAG => {
+                while open.last().is_some_and(|&(_, d)| d == depth) {
+                    open.pop();
+                    pass.replace(range.start..range.start, format!("\n\n{CLOSE_TAG}\n\n"));
+                }
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            // only the headings that are not in a list or a block quote
+            Event::Start(Tag::Heading(level, ..)) if range.start == 0 || source[..range.start].ends_with('\n') => {
+                level as usize
+            }
+            _ => continue,
+        };
+
+        while open.last().is_some_and(|&(l, d)| d == depth && l >= level) {
+            open.pop();
+            pass.replace(range.start..range.start, format!("\n\n{CLOSE_TAG}\n\n"));
+        }
+        open.push((level, depth));
+
+        let section = pass.open_tag(Embed::Section);
+        let summary = pass.open_tag(Embed::SectionSummary);
+        pass.replace(range.start..range.start, format!("\n{section}\n\n{summary}\n\n"));
+        let end = range.start + source[range.clone()].trim_end().len();
+        pass.replace(end..end, format!("\n\n{CLOSE_TAG}\n\n"));
+    }
+
+    for _ in open {
+        pass.replace(source.len()..source.len(), format!("\n\n{CLOSE_TAG}\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    #[wasm_bindgen_test]
+    fn unnumbered_markers() {
+        assert_eq!(unnumbered_marker("# A {-}\n"), Some((3..7, String::new())));
+        assert_eq!(unnumbered_marker("# A {.unnumbered #pre}\n"), Some((4..22, "{#pre}".to_string())));
+        assert_eq!(unnumbered_marker("# A {#pre}\n"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn unnumbered_headings() {
+        let src = "# Intro\n\n# Preface {-}\n\n## Part\n\n# Body\n";
+        let prepared = Prepared::new(&yew::props!(Props { src, number_sections: Some(1u8) }));
+        assert_eq!(
+            prepared.source,
+            "# <YewMdEmbed id=\"0\"/>Intro\n\n# Preface\n\n## <YewMdEmbed id=\"1\"/>Part\n\n# <YewMdEmbed id=\"2\"/>Body\n"
+        );
+        let entries: Vec<_> = prepared
+            .outline
+            .iter()
+            .map(|e| (e.level, e.number.as_deref(), e.text.as_str(), e.position.start))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (1, Some("1"), "Intro", 0),
+                (1, None, "Preface", 9),
+                (2, Some("1.1"), "Part", 24),
+                (1, Some("2"), "Body", 33),
+            ]
+        );
+        assert_eq!(prepared.original_range(91..95), 35..39);
+    }
+}
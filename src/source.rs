@@ -0,0 +1,208 @@
+//! rewriting of the markdown source before it is rendered.
+//! Each rewrite produces a [`SourceMap`], so that positions reported by
+//! `onclick` can still point into the source written by the user.
+
+use core::ops::Range;
+
+/// a part of the text that replaces a range
+pub(crate) enum Piece {
+    Text(String),
+    /// a copy of a range of the source, that keeps its positions
+    Copy(Range<usize>),
+}
+
+/// a list of replacements to apply on a source.
+pub(crate) struct Rewriter<'s> {
+    source: &'s str,
+    edits: Vec<(Range<usize>, Vec<Piece>)>,
+}
+
+impl<'s> Rewriter<'s> {
+    pub fn new(source: &'s str) -> Self {
+        Self {
+            source,
+            edits: Vec::new(),
+        }
+    }
+
+    /// replace `range` by `text`.
+    /// If the range overlaps a range that was already replaced, the edit is ignored.
+    pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) {
+        self.replace_with(range, vec![Piece::Text(text.into())])
+    }
+
+    /// replace `range` by `pieces`, put one after the other
+    pub fn replace_with(&mut self, range: Range<usize>, pieces: Vec<Piece>) {
+        self.edits.push((range, pieces))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn finish(mut self) -> (String, SourceMap) {
+        // stable sort: insertions at the same position keep their order,
+        // and come before a replacement starting there.
+        self.edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+        let mut output = String::with_capacity(self.source.len());
+        let mut map = SourceMap::default();
+        let mut cursor = 0;
+
+        for (range, pieces) in self.edits {
+            if range.start < cursor || range.end < range.start {
+                continue;
+            }
+            let start = output.len();
+            output.push_str(&self.source[cursor..range.start]);
+            map.push(start..output.len(), cursor..range.start, true);
+
+            for piece in pieces {
+                let start = output.len();
+                match piece {
+                    Piece::Text(text) => {
+                        output.push_str(&text);
+                        map.push(start..output.len(), range.clone(), false);
+                    }
+                    Piece::Copy(copied) => {
+                        output.push_str(&self.source[copied.clone()]);
+                        map.push(start..output.len(), copied, true);
+                    }
+                }
+            }
+            cursor = range.end;
+        }
+        let start = output.len();
+        output.push_str(&self.source[cursor..]);
+        map.push(start..output.len(), cursor..self.source.len(), true);
+
+        (output, map)
+    }
+}
+
+struct Segment {
+    output: Range<usize>,
+    original: Range<usize>,
+    /// whether the text was copied as-is from the original source
+    verbatim: bool,
+}
+
+/// the correspondence between a rewritten source and the original one.
+#[derive(Default)]
+pub(crate) struct SourceMap {
+    segments: Vec<Segment>,
+}
+
+impl SourceMap {
+    fn push(&mut self, output: Range<usize>, original: Range<usize>, verbatim: bool) {
+        // removed text does not correspond to any output position
+        if output.is_empty() {
+            return;
+        }
+        self.segments.push(Segment {
+            output,
+            original,
+            verbatim,
+        })
+    }
+
+    fn original_start(&self, x: usize) -> usize {
+        let i = self.segments.partition_point(|s| s.output.end <= x);
+        match self.segments.get(i) {
+            Some(s) if s.verbatim => s.original.start + x.saturating_sub(s.output.start),
+            Some(s) => s.original.start,
+            None => self.segments.last().map_or(x, |s| s.original.end),
+        }
+    }
+
+    fn original_end(&self, x: usize) -> usize {
+        let i = self.segments.partition_point(|s| s.output.end < x);
+        match self.segments.get(i) {
+            Some(s) if s.verbatim => s.original.start + x.saturating_sub(s.output.start),
+            Some(s) => s.original.end,
+            None => self.segments.last().map_or(x, |s| s.original.end),
+        }
+    }
+
+    /// the range in the original source that produced `range`
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.original_start(range.start);
+        let end = self.original_end(range.end).max(start);
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    fn rewrite(source: &str, edit: impl FnOnce(&mut Rewriter)) -> (String, SourceMap) {
+        let mut rewriter = Rewriter::new(source);
+        edit(&mut rewriter);
+        rewriter.finish()
+    }
+
+    #[wasm_bindgen_test]
+    fn without_edits_positions_are_unchanged() {
+        let (output, map) = rewrite("hello world", |_| {});
+        assert_eq!(output, "hello world");
+        assert_eq!(map.original_range(6..11), 6..11);
+    }
+
+    #[wasm_bindgen_test]
+    fn replaced_text_points_to_what_it_replaces() {
+        let (output, map) = rewrite("hello world", |r| r.replace(6..11, "there!"));
+        assert_eq!(output, "hello there!");
+        assert_eq!(map.original_range(0..5), 0..5);
+        assert_eq!(map.original_range(6..12), 6..11);
+        assert_eq!(map.original_range(8..9), 6..11);
+    }
+
+    #[wasm_bindgen_test]
+    fn text_after_an_insertion_is_shifted_back() {
+        let (output, map) = rewrite("abc", |r| r.replace(0..0, "> "));
+        assert_eq!(output, "> abc");
+        assert_eq!(map.original_range(2..5), 0..3);
+    }
+
+    #[wasm_bindgen_test]
+    fn text_after_a_removal_is_shifted_forward() {
+        let (output, map) = rewrite("abcdef", |r| r.replace(2..4, ""));
+        assert_eq!(output, "abef");
+        assert_eq!(map.original_range(2..3), 4..5);
+    }
+
+    #[wasm_bindgen_test]
+    fn copied_pieces_keep_their_positions() {
+        let (output, map) = rewrite("abcdef", |r| {
+            r.replace_with(
+                0..6,
+                vec![Piece::Text("[".to_string()), Piece::Copy(2..4), Piece::Text("]".to_string())],
+            )
+        });
+        assert_eq!(output, "[cd]");
+        assert_eq!(map.original_range(1..3), 2..4);
+        assert_eq!(map.original_range(0..1), 0..6);
+    }
+
+    #[wasm_bindgen_test]
+    fn overlapping_edits_are_ignored() {
+        let (output, _) = rewrite("abcdef", |r| {
+            r.replace(2..5, "Y");
+            r.replace(1..4, "X");
+        });
+        assert_eq!(output, "aXef");
+    }
+
+    #[wasm_bindgen_test]
+    fn insertions_keep_their_order_before_a_replacement() {
+        let (output, _) = rewrite("abcdef", |r| {
+            r.replace(2..4, "Z");
+            r.replace(2..2, "1");
+            r.replace(2..2, "2");
+        });
+        assert_eq!(output, "ab12Zef");
+    }
+}
@@ -0,0 +1,71 @@
+//! spoilers: `||inline||` and `:::spoiler` blocks, blurred until they are revealed
+//! with a click, `Enter` or `Space`.
+
+use yew::prelude::{classes, function_component, html, use_state, Callback, Html, KeyboardEvent, MouseEvent, Properties};
+
+#[derive(PartialEq, Properties)]
+pub(crate) struct SpoilerProps {
+    pub block: bool,
+    /// announced before the content is revealed
+    #[prop_or_default]
+    pub label: Option<String>,
+    /// called for the clicks once the content is revealed
+    pub onclick: Callback<MouseEvent>,
+    pub children: Html,
+}
+
+#[function_component]
+pub(crate) fn Spoiler(props: &SpoilerProps) -> Html {
+    let revealed = use_state(|| false);
+
+    let onclick = {
+        let revealed = revealed.clone();
+        let report = props.onclick.clone();
+        Callback::from(move |e: MouseEvent| match *revealed {
+            true => report.emit(e),
+            false => {
+                e.stop_propagation();
+                revealed.set(true)
+            }
+        })
+    };
+    let onkeydown = {
+        let revealed = revealed.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if !*revealed && (e.key() == "Enter" || e.key() == " ") {
+                e.prevent_default();
+                revealed.set(true)
+            }
+        })
+    };
+
+    let class = classes!("md-spoiler", revealed.then_some("md-spoiler-revealed"));
+    let label = props.label.clone().unwrap_or_else(|| "Spoiler".to_string());
+    // once revealed, the content is read like the rest of the document
+    let (role, tabindex, aria_label) = match *revealed {
+        true => (None, None, None),
+        false => (Some("button"), Some("0"), Some(format!("{label}, activate to reveal"))),
+    };
+    // until it is revealed, the links and inputs of the content can not be reached with the keyboard
+    let inert = (!*revealed).then_some("");
+    let content = html! {
+        <@{if props.block { "div" } else { "span" }} class="md-spoiler-content" aria-hidden={(!*revealed).to_string()} {inert}>
+            {props.children.clone()}
+        </@>
+    };
+
+    if props.block {
+        html! {
+            <div {class} {role} {tabindex} aria-label={aria_label} {onclick} {onkeydown}>
+                if let Some(label) = &props.label {
+                    <p class="md-spoiler-label">{label.clone()}</p>
+                }
+                {content}
+            </div>
+        }
+    } else {
+        html! {
+            <span {class} {role} {tabindex} aria-label={aria_label} {onclick} {onkeydown}>{content}</span>
+        }
+    }
+}
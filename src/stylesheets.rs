@@ -0,0 +1,164 @@
+//! the stylesheets that `Markdown` components mount in `<head>`.
+//!
+//! A stylesheet is mounted once, no matter how many components use it,
+//! and removed when the last of them is unmounted or stops using it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use web_sys::{window, Element};
+
+use yew::prelude::AttrValue;
+
+/// the theme of code blocks highlighted with classes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HighlightTheme {
+    /// always use this theme
+    Fixed(AttrValue),
+    /// follow the `prefers-color-scheme` of the browser
+    System { light: AttrValue, dark: AttrValue },
+}
+
+/// the css of the elements that yew-markdown adds to markdown, like numbered equations.
+/// It is mounted by the `Markdown` component, unless `dynamic_stylesheets` is `false`.
+pub const STYLESHEET: &str = include_str!("markdown.css");
+
+/// the katex stylesheet, the one that `rust-web-markdown` requests for the formulas it renders
+#[cfg(feature = "math")]
+pub(crate) const KATEX_STYLESHEET: &str = "https://cdn.jsdelivr.net/npm/katex@0.16.7/dist/katex.min.css";
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Stylesheet {
+    /// [`STYLESHEET`]
+    Base,
+    /// a `<link>`, like the ones requested by `rust-web-markdown`
+    Link {
+        rel: String,
+        href: String,
+        integrity: String,
+        crossorigin: String,
+    },
+    /// the colors of the code highlighted with classes
+    HighlightTheme(HighlightTheme),
+}
+
+impl Stylesheet {
+    /// two stylesheets with the same key are the same element in `<head>`
+    fn key(&self) -> String {
+        match self {
+            Stylesheet::Base => "yew-markdown".to_string(),
+            Stylesheet::Link { href, .. } => href.clone(),
+            Stylesheet::HighlightTheme(HighlightTheme::Fixed(theme)) => format!("highlight-theme:{theme}"),
+            Stylesheet::HighlightTheme(HighlightTheme::System { light, dark }) => {
+                format!("highlight-theme:{light}:{dark}")
+            }
+        }
+    }
+
+    fn create_element(&self, nonce: Option<&str>) -> Option<Element> {
+        let document = window()?.document()?;
+        let element = match self {
+            Stylesheet::Base => {
+                let style = document.create_element("style").ok()?;
+                style.set_text_content(Some(STYLESHEET));
+                style
+            }
+            Stylesheet::Link {
+                rel,
+                href,
+                integrity,
+                crossorigin,
+            } => {
+                let link = document.create_element("link").ok()?;
+                link.set_attribute("rel", rel).ok()?;
+                link.set_attribute("href", href).ok()?;
+                if !integrity.is_empty() {
+                    link.set_attribute("integrity", integrity).ok()?;
+                }
+                if !crossorigin.is_empty() {
+                    link.set_attribute("crossorigin", crossorigin).ok()?;
+                }
+                link
+            }
+            Stylesheet::HighlightTheme(theme) => {
+                let style = document.create_element("style").ok()?;
+                style.set_text_content(Some(&theme_css(theme)?));
+                style
+            }
+        };
+        if let Some(nonce) = nonce {
+            element.set_attribute("nonce", nonce).ok()?;
+        }
+        Some(element)
+    }
+}
+
+#[cfg(feature = "highlight")]
+fn theme_css(theme: &HighlightTheme) -> Option<String> {
+    use crate::highlight::theme_stylesheet;
+    match theme {
+        HighlightTheme::Fixed(theme) => theme_stylesheet(theme),
+        HighlightTheme::System { light, dark } => Some(format!(
+            "@media (prefers-color-scheme: light) {{\n{}\n}}\n@media (prefers-color-scheme: dark) {{\n{}\n}}\n",
+            theme_stylesheet(light)?,
+            theme_stylesheet(dark)?,
+        )),
+    }
+}
+
+#[cfg(not(feature = "highlight"))]
+fn theme_css(_theme: &HighlightTheme) -> Option<String> {
+    None
+}
+
+struct Mounted {
+    element: Element,
+    users: usize,
+}
+
+thread_local! {
+    static MOUNTED: RefCell<HashMap<String, Mounted>> = RefCell::new(HashMap::new());
+}
+
+/// the href of a stylesheet served from `base` instead of its original location.
+/// Only the file name is kept, so `https://cdn.jsdelivr.net/npm/katex@0.16.7/dist/katex.min.css`
+/// becomes `{base}/katex.min.css`.
+pub(crate) fn rebase(href: &str, base: &str) -> String {
+    let file_name = href.rsplit('/').next().unwrap_or(href);
+    format!("{}/{}", base.trim_end_matches('/'), file_name)
+}
+
+/// mount `stylesheet` if it is not already, and count one more user.
+/// The `nonce` is only used if the stylesheet is not mounted yet.
+pub(crate) fn acquire(stylesheet: &Stylesheet, nonce: Option<&str>) {
+    MOUNTED.with_borrow_mut(|mounted| {
+        let key = stylesheet.key();
+        if let Some(m) = mounted.get_mut(&key) {
+            m.users += 1;
+            return;
+        }
+        let Some(element) = stylesheet.create_element(nonce) else {
+            return;
+        };
+        let head = window().and_then(|w| w.document()).and_then(|d| d.head());
+        if let Some(head) = head {
+            if head.append_child(&element).is_ok() {
+                mounted.insert(key, Mounted { element, users: 1 });
+            }
+        }
+    })
+}
+
+/// count one less user of `stylesheet`, and remove it if it was the last one
+pub(crate) fn release(stylesheet: &Stylesheet) {
+    MOUNTED.with_borrow_mut(|mounted| {
+        let key = stylesheet.key();
+        if let Some(m) = mounted.get_mut(&key) {
+            m.users -= 1;
+            if m.users == 0 {
+                m.element.remove();
+                mounted.remove(&key);
+            }
+        }
+    })
+}
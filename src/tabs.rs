@@ -0,0 +1,191 @@
+//! the components of the `:::tabs` containers, see [`crate::containers`].
+//!
+//! With `sync_tabs`, choosing a tab chooses the tab with the same title in
+//! every group of the page, and the choice is remembered in the local storage.
+
+use std::cell::{Cell, RefCell};
+
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement};
+use yew::prelude::{
+    function_component, html, use_context, use_effect_with, use_memo, use_state, AttrValue, Callback,
+    ContextProvider, Html, KeyboardEvent, MouseEvent, Properties,
+};
+
+/// the key of the title of the chosen tab in the local storage
+const STORAGE_KEY: &str = "yew-markdown-tab";
+
+thread_local! {
+    static NEXT_ID: Cell<usize> = const { Cell::new(0) };
+    /// the groups that follow the chosen tab, by id
+    static SYNCED: RefCell<Vec<(AttrValue, Callback<String>)>> = RefCell::new(Vec::new());
+}
+
+fn stored_title() -> Option<String> {
+    window()?.local_storage().ok()??.get_item(STORAGE_KEY).ok()?
+}
+
+fn store_title(title: &str) {
+    if let Some(Ok(Some(storage))) = window().map(|w| w.local_storage()) {
+        let _ = storage.set_item(STORAGE_KEY, title);
+    }
+}
+
+/// choose the tab `title` in every synchronized group
+fn choose_everywhere(title: &str) {
+    store_title(title);
+    let groups = SYNCED.with_borrow(|groups| groups.clone());
+    for (_, group) in groups {
+        group.emit(title.to_string())
+    }
+}
+
+fn focus(id: &str) {
+    let element = window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(id))
+        .and_then(|e| e.dyn_into::<HtmlElement>().ok());
+    if let Some(element) = element {
+        let _ = element.focus();
+    }
+}
+
+/// what the panels of a group need to know
+#[derive(Clone, PartialEq)]
+struct Group {
+    id: AttrValue,
+    selected: usize,
+}
+
+fn tab_id(group: &str, index: usize) -> String {
+    format!("{group}-tab-{index}")
+}
+
+fn panel_id(group: &str, index: usize) -> String {
+    format!("{group}-panel-{index}")
+}
+
+#[derive(PartialEq, Properties)]
+pub(crate) struct TabGroupProps {
+    pub titles: Vec<String>,
+    pub sync: bool,
+    pub children: Html,
+}
+
+/// the list of tabs, and the panels in the children
+#[function_component]
+pub(crate) fn TabGroup(props: &TabGroupProps) -> Html {
+    let id = use_memo((), |_| {
+        let id = NEXT_ID.get();
+        NEXT_ID.set(id + 1);
+        AttrValue::from(format!("md-tabs-{id}"))
+    });
+    let selected = use_state(|| {
+        let stored = props.sync.then(stored_title).flatten();
+        stored
+            .and_then(|title| props.titles.iter().position(|t| *t == title))
+            .unwrap_or(0)
+    });
+
+    {
+        let selected = selected.clone();
+        let id = (*id).clone();
+        // registered again when the titles change, so that the group follows its current tabs
+        use_effect_with((props.sync, props.titles.clone()), move |(sync, titles)| {
+            if *sync {
+                let titles = titles.clone();
+                let follow = Callback::from(move |title: String| {
+                    if let Some(i) = titles.iter().position(|t| *t == title) {
+                        selected.set(i)
+                    }
+                });
+                SYNCED.with_borrow_mut(|groups| groups.push((id.clone(), follow)));
+            }
+            move || SYNCED.with_borrow_mut(|groups| groups.retain(|(i, _)| *i != id))
+        });
+    }
+
+    let choose = {
+        let selected = selected.clone();
+        let titles = props.titles.clone();
+        let sync = props.sync;
+        Callback::from(move |i: usize| {
+            selected.set(i);
+            if sync {
+                choose_everywhere(&titles[i])
+            }
+        })
+    };
+
+    // the arrows, `Home` and `End` move between the tabs
+    let onkeydown = {
+        let choose = choose.clone();
+        let id = (*id).clone();
+        let len = props.titles.len();
+        let current = *selected;
+        Callback::from(move |e: KeyboardEvent| {
+            if len == 0 {
+                return;
+            }
+            let next = match e.key().as_str() {
+                "ArrowRight" => (current + 1) % len,
+                "ArrowLeft" => (current + len - 1) % len,
+                "Home" => 0,
+                "End" => len - 1,
+                _ => return,
+            };
+            e.prevent_default();
+            choose.emit(next);
+            focus(&tab_id(&id, next));
+        })
+    };
+
+    let group = Group {
+        id: (*id).clone(),
+        selected: *selected,
+    };
+    html! {
+        <div class="md-tabs">
+            <div class="md-tablist" role="tablist" {onkeydown}>
+                {for props.titles.iter().enumerate().map(|(i, title)| {
+                    let active = i == *selected;
+                    html! {
+                        <button type="button" role="tab" class="md-tab"
+                            id={tab_id(&id, i)}
+                            aria-selected={active.to_string()}
+                            aria-controls={panel_id(&id, i)}
+                            tabindex={if active { "0" } else { "-1" }}
+                            onclick={choose.reform(move |_: MouseEvent| i)}>
+                            {title.clone()}
+                        </button>
+                    }
+                })}
+            </div>
+            <ContextProvider<Group> context={group}>
+                {props.children.clone()}
+            </ContextProvider<Group>>
+        </div>
+    }
+}
+
+#[derive(PartialEq, Properties)]
+pub(crate) struct TabPanelProps {
+    pub index: usize,
+    pub children: Html,
+}
+
+/// the content of a tab, hidden when the tab is not chosen
+#[function_component]
+pub(crate) fn TabPanel(props: &TabPanelProps) -> Html {
+    let Some(group) = use_context::<Group>() else {
+        return props.children.clone();
+    };
+    html! {
+        <div class="md-tabpanel" role="tabpanel" tabindex="0"
+            id={panel_id(&group.id, props.index)}
+            aria-labelledby={tab_id(&group.id, props.index)}
+            hidden={props.index != group.selected}>
+            {props.children.clone()}
+        </div>
+    }
+}
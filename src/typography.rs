@@ -0,0 +1,157 @@
+//! typographic replacements in the text: curly quotes, dashes, ellipses and symbols.
+//! A character escaped with `\` is left as it is.
+
+use pulldown_cmark::{LinkType, Tag};
+
+use crate::prepare::Pass;
+
+/// the replacements made in the text, as given to the `typography` of [`Props`](crate::Props).
+/// The quotes depend on the language, see [`Typography::english`],
+/// [`Typography::french`] and [`Typography::german`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Typography {
+    /// the opening and closing quotes that replace `"`
+    pub double_quotes: (String, String),
+    /// the opening and closing quotes that replace `'`.
+    /// After a letter, `'` is an apostrophe: `’`
+    pub single_quotes: (String, String),
+    /// `--` and `---` become `–` and `—`
+    pub dashes: bool,
+    /// `...` becomes `…`
+    pub ellipses: bool,
+    /// `(c)`, `(r)` and `(tm)` become `©`, `®` and `™`
+    pub symbols: bool,
+    /// the space before `;`, `:`, `!` and `?` becomes a non-breaking space, like in French
+    pub french_spacing: bool,
+}
+
+impl Typography {
+    /// “double” and ‘single’ quotes
+    pub fn english() -> Self {
+        Self {
+            double_quotes: ("“".to_string(), "”".to_string()),
+            single_quotes: ("‘".to_string(), "’".to_string()),
+            dashes: true,
+            ellipses: true,
+            symbols: true,
+            french_spacing: false,
+        }
+    }
+
+    /// « guillemets », with non-breaking spaces inside them and before `;:!?`
+    pub fn french() -> Self {
+        Self {
+            double_quotes: ("«\u{a0}".to_string(), "\u{a0}»".to_string()),
+            single_quotes: ("‹\u{a0}".to_string(), "\u{a0}›".to_string()),
+            french_spacing: true,
+            ..Self::english()
+        }
+    }
+
+    /// „double“ and ‚single‘ quotes
+    pub fn german() -> Self {
+        Self {
+            double_quotes: ("„".to_string(), "“".to_string()),
+            single_quotes: ("‚".to_string(), "‘".to_string()),
+            ..Self::english()
+        }
+    }
+
+    /// the replacement at the start of `text`, and the length it replaces.
+    /// `before` is the character before `text`.
+    fn replacement(&self, text: &str, before: Option<char>) -> Option<(usize, String)> {
+        let fixed = |len: usize, with: &str| Some((len, with.to_string()));
+        if self.dashes {
+            if text.starts_with("---") {
+                return fixed(3, "—");
+            }
+            if text.starts_with("--") {
+                return fixed(2, "–");
+            }
+        }
+        if self.ellipses && text.starts_with("...") {
+            return fixed(3, "…");
+        }
+        if self.symbols {
+            let symbols = [("(c)", "©"), ("(r)", "®"), ("(tm)", "™")];
+            for (code, symbol) in symbols {
+                if text.get(..code.len()).is_some_and(|t| t.eq_ignore_ascii_case(code)) {
+                    return fixed(code.len(), symbol);
+                }
+            }
+        }
+
+        let opening = before.map_or(true, |c| c.is_whitespace() || "([{-–—".contains(c));
+        let quote = |(open, close): &(String, String)| Some((1, if opening { open } else { close }.clone()));
+        match text.chars().next()? {
+            '"' => quote(&self.double_quotes),
+            '\'' if before.is_some_and(char::is_alphanumeric) => fixed(1, "’"),
+            '\'' => quote(&self.single_quotes),
+            ' ' if self.french_spacing => match text[1..].chars().next()? {
+                ':' => fixed(1, "\u{a0}"),
+                ';' | '!' | '?' => fixed(1, "\u{202f}"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+/// make the replacements in the text, outside of code and of `<...>` links
+pub(crate) fn typography(pass: &mut Pass, typography: &Typography) {
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(LinkType::Autolink, ..)));
+    let found = pass.scan(&runs, |before, text| typography.replacement(text, before.chars().next_back()));
+    for (range, with) in found {
+        pass.replace(range, with);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    fn dashes_ellipses_and_symbols() {
+        let t = Typography::english();
+        assert_eq!(t.replacement("---x", None), Some((3, "—".to_string())));
+        assert_eq!(t.replacement("--x", None), Some((2, "–".to_string())));
+        assert_eq!(t.replacement("...", None), Some((3, "…".to_string())));
+        assert_eq!(t.replacement("(TM)", None), Some((4, "™".to_string())));
+        assert_eq!(t.replacement("x", None), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn quotes_open_after_spaces_and_close_after_words() {
+        let t = Typography::english();
+        assert_eq!(t.replacement("\"a", None), Some((1, "“".to_string())));
+        assert_eq!(t.replacement("\"a", Some('(')), Some((1, "“".to_string())));
+        assert_eq!(t.replacement("\" ", Some('a')), Some((1, "”".to_string())));
+        assert_eq!(t.replacement("'a", Some(' ')), Some((1, "‘".to_string())));
+        assert_eq!(t.replacement("'s", Some('t')), Some((1, "’".to_string())));
+    }
+
+    #[wasm_bindgen_test]
+    fn french_quotes_and_spaces() {
+        let t = Typography::french();
+        assert_eq!(t.replacement("\"a", None), Some((1, "«\u{a0}".to_string())));
+        assert_eq!(t.replacement(" :", Some('a')), Some((1, "\u{a0}".to_string())));
+        assert_eq!(t.replacement(" ?", Some('a')), Some((1, "\u{202f}".to_string())));
+        assert_eq!(Typography::english().replacement(" ?", Some('a')), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn german_quotes() {
+        let t = Typography::german();
+        assert_eq!(t.replacement("\"a", None), Some((1, "„".to_string())));
+        assert_eq!(t.replacement("\" ", Some('a')), Some((1, "“".to_string())));
+    }
+}
@@ -0,0 +1,143 @@
+//! template variables: each `{{name}}` or `{{ name }}` of the source is replaced
+//! by the value of `name`, in the text as well as in link targets and code.
+//! A placeholder escaped with `\` is left as it is.
+//!
+//! In the text, a value is plain text: what markdown would read as syntax is escaped.
+//! Elsewhere, like in code, link targets or html, it is inserted as it is.
+
+use std::collections::HashMap;
+
+use yew::prelude::AttrValue;
+
+use crate::prepare::Pass;
+
+/// the characters of a value that are escaped in the text
+const SYNTAX: &str = "\\`*_[]<>!&#|~^=+-$";
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-.".contains(c)
+}
+
+/// the name of the placeholder at the start of `text`, and its length
+fn parse(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("{{")?;
+    let end = rest.find("}}")?;
+    let name = rest[..end].trim();
+    (!name.is_empty() && name.chars().all(is_name_char)).then_some((name, end + 4))
+}
+
+/// `value` as plain text: the characters that markdown could read as syntax are escaped,
+/// and the line breaks become spaces so that the value stays in its paragraph
+fn escape(value: &str) -> String {
+    let digits = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.char_indices() {
+        match c {
+            '\r' => continue,
+            '\n' => {
+                escaped.push(' ');
+                continue;
+            }
+            _ => {}
+        }
+        // `1.` or `1)` at the start of a line would be a list item
+        let list_marker = digits > 0 && i == digits && (c == '.' || c == ')');
+        if SYNTAX.contains(c) || list_marker {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// replace the placeholders by their values.
+/// The placeholders of unknown variables stay as they are.
+pub(crate) fn variables(pass: &mut Pass, variables: &HashMap<String, AttrValue>) {
+    let source = pass.source;
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |_| false);
+    let mut i = 0;
+    while let Some(found) = source[i..].find("{{") {
+        i += found;
+        let escaped = source[..i].ends_with('\\');
+        let Some((name, len)) = parse(&source[i..]).filter(|_| !escaped) else {
+            i += 2;
+            continue;
+        };
+        let range = i..i + len;
+        match variables.get(name) {
+            Some(value) => {
+                let in_text = runs.iter().any(|r| r.start <= range.start && range.end <= r.end);
+                let value = match in_text {
+                    true => escape(value),
+                    false => value.to_string(),
+                };
+                pass.replace(range, value)
+            }
+            None => pass.diagnostic(range, format!("the variable `{name}` is not defined")),
+        }
+        i += len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn substitute(src: &'static str) -> Prepared {
+        let variables = HashMap::from([
+            ("name".to_string(), AttrValue::from("<Acme> *NIX*")),
+            ("host".to_string(), AttrValue::from("example.com")),
+        ]);
+        Prepared::new(&yew::props!(Props { src, variables }))
+    }
+
+    #[wasm_bindgen_test]
+    fn placeholders() {
+        assert_eq!(parse("{{name}} and"), Some(("name", 8)));
+        assert_eq!(parse("{{ name }}"), Some(("name", 10)));
+        assert_eq!(parse("{{two words}}"), None);
+        assert_eq!(parse("{{}}"), None);
+        assert_eq!(parse("{{name"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn values_are_escaped() {
+        assert_eq!(escape("<Acme>"), "\\<Acme\\>");
+        assert_eq!(escape("*NIX*"), "\\*NIX\\*");
+        assert_eq!(escape("a_b [c]"), "a\\_b \\[c\\]");
+        assert_eq!(escape("one\r\ntwo"), "one two");
+        assert_eq!(escape("1. first"), "1\\. first");
+        assert_eq!(escape("version 1.2"), "version 1.2");
+    }
+
+    #[wasm_bindgen_test]
+    fn values_are_text_in_the_text() {
+        let prepared = substitute("Hello {{name}}!\n");
+        assert_eq!(prepared.source, "Hello \\<Acme\\> \\*NIX\\*!\n");
+        // the value points to its placeholder
+        assert_eq!(prepared.original_range(6..22), 6..14);
+        assert_eq!(prepared.original_range(22..23), 14..15);
+    }
+
+    #[wasm_bindgen_test]
+    fn values_are_raw_in_code_and_link_targets() {
+        let prepared = substitute("`{{name}}` [site](https://{{ host }}/a)\n\n```\n{{name}}\n```\n");
+        assert_eq!(
+            prepared.source,
+            "`<Acme> *NIX*` [site](https://example.com/a)\n\n```\n<Acme> *NIX*\n```\n"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn escaped_and_unknown_placeholders_stay() {
+        let prepared = substitute("\\{{name}} {{unknown}}\n");
+        assert_eq!(prepared.source, "\\{{name}} {{unknown}}\n");
+        assert_eq!(prepared.diagnostics.len(), 1);
+        assert_eq!(prepared.diagnostics[0].position, 10..21);
+    }
+}
@@ -0,0 +1,421 @@
+//! numbered elements, and the references to them.
+//!
+//! A display formula is numbered when it contains `\label{eq:...}`.
+//! It can be referenced with `\eqref{eq:...}` or `[@eq:...]`.
+//! A label without the `eq:` prefix is removed from the formula, with a diagnostic.
+//!
+//! An image alone on its line is a figure when it is followed by a `{#fig:...}` label,
+//! or, with `figure_captions`, by a caption on the next lines.
+//! Without a caption, its alternative text is the caption.
+//! A table is captioned by a paragraph right after it, starting with `Table:` or `:`.
+//! Figures and tables with a caption are numbered, and referenced with `[@fig:...]` or `[@tbl:...]`.
+
+use core::ops::Range;
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::embed::{Embed, CLOSE_TAG};
+use crate::math::MathBlock;
+use crate::prepare::Pass;
+
+/// what a label points to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum TargetKind {
+    Equation,
+    Figure,
+    Table,
+}
+
+impl TargetKind {
+    /// the kind of element a label is meant for, from its prefix
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label.split_once(':')?.0 {
+            "eq" => Some(TargetKind::Equation),
+            "fig" => Some(TargetKind::Figure),
+            "tbl" => Some(TargetKind::Table),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TargetKind::Equation => "Equation",
+            TargetKind::Figure => "Figure",
+            TargetKind::Table => "Table",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Target {
+    pub kind: TargetKind,
+    pub number: usize,
+}
+
+/// the numbered elements of a document
+#[derive(Default)]
+pub(crate) struct Targets {
+    labels: HashMap<String, Target>,
+    counts: HashMap<TargetKind, usize>,
+}
+
+impl Targets {
+    /// the next number for an element of this kind
+    pub fn next_number(&mut self, kind: TargetKind) -> usize {
+        let count = self.counts.entry(kind).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// give the next number of its kind to `label`.
+    /// Returns `None` if the label is already used.
+    pub fn add(&mut self, kind: TargetKind, label: &str) -> Option<usize> {
+        if self.labels.contains_key(label) {
+            return None;
+        }
+        let number = self.next_number(kind);
+        self.labels.insert(label.to_string(), Target { kind, number });
+        Some(number)
+    }
+
+    /// number an element that may have a label, and report when the label is already used
+    fn number(&mut self, pass: &mut Pass, kind: TargetKind, label: Option<&str>, range: Range<usize>) -> usize {
+        match label {
+            Some(label) => self.add(kind, label).unwrap_or_else(|| {
+                pass.diagnostic(range, format!("the label `{label}` is used more than once"));
+                self.next_number(kind)
+            }),
+            None => self.next_number(kind),
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Option<Target> {
+        self.labels.get(label).copied()
+    }
+}
+
+/// the label of a formula, and the formula without it
+fn take_label(tex: &str) -> Option<(String, String)> {
+    let start = tex.find("\\label{")?;
+    let label_start = start + "\\label{".len();
+    let label_end = label_start + tex[label_start..].find('}')?;
+    let label = tex[label_start..label_end].trim().to_string();
+    let tex = format!("{}{}", &tex[..start], &tex[label_end + 1..]);
+    Some((label, tex))
+}
+
+/// number the display formulas with a label
+pub(crate) fn equations(pass: &mut Pass, targets: &mut Targets) {
+    for (event, range) in pass.events() {
+        let Event::DisplayMath(tex) = event else {
+            continue;
+        };
+        let Some((label, tex)) = take_label(&tex) else {
+            continue;
+        };
+        let math = MathBlock {
+            tex,
+            display: true,
+            position: pass.original_range(range.clone()),
+        };
+        if TargetKind::from_label(&label) != Some(TargetKind::Equation) {
+            pass.diagnostic(range.clone(), format!("the label `{label}` of a formula must start with `eq:`"));
+            pass.embed_inline(range, Embed::Math(math));
+            continue;
+        }
+        let number = targets.number(pass, TargetKind::Equation, Some(&label), range.clone());
+        pass.embed_inline(range, Embed::Equation { math, label, number });
+    }
+}
+
+/// the label in a `{#...}` attribute
+fn label_attribute(s: &str) -> Option<&str> {
+    let inner = s.trim().strip_prefix("{#")?.strip_suffix('}')?;
+    inner.split_whitespace().next()
+}
+
+/// make figures out of the images with a caption or a label,
+/// and add the captions to the tables
+pub(crate) fn figures(pass: &mut Pass, targets: &mut Targets, captions: bool) {
+    let events = pass.events();
+    for (i, (event, range)) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Paragraph) => figure(pass, targets, &events[i..], range.clone(), captions),
+            Event::Start(Tag::Table(_)) => {
+                // the caption is the paragraph right after the table
+                let end = i + events[i..]
+                    .iter()
+                    .position(|(e, _)| matches!(e, Event::End(Tag::Table(_))))
+                    .unwrap_or(0);
+                if let Some((Event::Start(Tag::Paragraph), caption)) = events.get(end + 1) {
+                    table_caption(pass, targets, range.clone(), caption.clone())
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `events` start with the paragraph at `range`
+fn figure(
+    pass: &mut Pass,
+    targets: &mut Targets,
+    events: &[(Event, Range<usize>)],
+    range: Range<usize>,
+    captions: bool,
+) {
+    let Some((Event::Start(Tag::Image(_, src, title)), image)) = events.get(1) else {
+        return;
+    };
+    let source = pass.source;
+    let end = range.start + source[range.clone()].trim_end().len();
+    let line_end = source[range.start..end]
+        .find('\n')
+        .map_or(end, |i| range.start + i);
+    if image.start != range.start || image.end > line_end {
+        return;
+    }
+
+    let attribute = source[image.end..line_end].trim();
+    let label = match attribute {
+        "" => None,
+        _ => match label_attribute(attribute) {
+            Some(label) => Some(label),
+            None => return,
+        },
+    };
+    let has_caption = captions && line_end < end;
+    if label.is_none() && !has_caption {
+        return;
+    }
+
+    let alt: String = events[2..]
+        .iter()
+        .map_while(|(e, _)| match e {
+            Event::Text(t) => Some(t.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    let number = targets.number(pass, TargetKind::Figure, label, range.clone());
+    let figure = Embed::Figure {
+        src: src.to_string(),
+        title: title.to_string(),
+        // the caption is the alternative text if there is no caption under the image
+        caption: (!has_caption).then(|| alt.clone()),
+        alt,
+        label: label.map(str::to_string),
+        number,
+        position: pass.original_range(range.clone()),
+    };
+
+    if has_caption {
+        let tags = [
+            pass.open_tag(figure),
+            pass.open_tag(Embed::Caption {
+                kind: TargetKind::Figure,
+                number,
+            }),
+        ];
+        let open = pass.block_lines(range.start, &tags);
+        let close = pass.block_lines(range.start, &[CLOSE_TAG, CLOSE_TAG]);
+        pass.replace(range.start..line_end, open);
+        pass.replace(end..end, close);
+    } else {
+        // the lines after the image stay a paragraph
+        let tag = pass.tag(figure);
+        let text = pass.block_lines(range.start, &[tag]);
+        pass.replace(range.start..line_end, text);
+    }
+}
+
+fn table_caption(pass: &mut Pass, targets: &mut Targets, table: Range<usize>, caption: Range<usize>) {
+    let source = pass.source;
+    let end = caption.start + source[caption.clone()].trim_end().len();
+    let text = &source[caption.start..end];
+
+    let Some(rest) = text.strip_prefix("Table:").or_else(|| text.strip_prefix(':')) else {
+        return;
+    };
+    if !rest.starts_with([' ', '\t']) {
+        return;
+    }
+    let content_start = end - rest.trim_start().len();
+
+    let (label, content_end) = match text.rfind("{#") {
+        Some(i) if text.ends_with('}') => match label_attribute(&text[i..]) {
+            Some(label) => (Some(label), caption.start + text[..i].trim_end().len()),
+            None => (None, end),
+        },
+        _ => (None, end),
+    };
+
+    let number = targets.number(pass, TargetKind::Table, label, table.clone());
+    let open_table = pass.open_tag(Embed::TableFigure {
+        label: label.map(str::to_string),
+    });
+    let open_caption = pass.open_tag(Embed::Caption {
+        kind: TargetKind::Table,
+        number,
+    });
+
+    let text = pass.block_lines(table.start, &[open_table]);
+    pass.replace(table.start..table.start, text);
+    let text = pass.block_lines(caption.start, &[open_caption]);
+    pass.replace(caption.start..content_start, text);
+    pass.replace(content_end..end, "");
+    let text = pass.block_lines(caption.start, &[CLOSE_TAG, CLOSE_TAG]);
+    pass.replace(end..end, text);
+}
+
+/// a reference found in some text
+struct Reference<'s> {
+    label: &'s str,
+    /// `true` for `\eqref{...}`, `false` for `[@...]`
+    eqref: bool,
+    /// the length of the reference in the source
+    len: usize,
+}
+
+fn parse_reference(s: &str) -> Option<Reference<'_>> {
+    if let Some(rest) = s.strip_prefix("\\eqref{") {
+        let end = rest.find('}')?;
+        return Some(Reference {
+            label: rest[..end].trim(),
+            eqref: true,
+            len: "\\eqref{".len() + end + 1,
+        });
+    }
+    let rest = s.strip_prefix("[@")?;
+    let end = rest.find(']')?;
+    let label = &rest[..end];
+    // only labels of numbered elements, `[@...]` is also used for citations
+    TargetKind::from_label(label)?;
+    if label.contains(|c: char| c.is_whitespace() || c == ',' || c == ';') {
+        return None;
+    }
+    Some(Reference {
+        label,
+        eqref: false,
+        len: "[@".len() + end + 1,
+    })
+}
+
+/// replace the references by links to the numbered elements
+pub(crate) fn references(pass: &mut Pass, targets: &Targets) {
+    let events = pass.events();
+    let runs = pass.text_runs(&events, |tag| matches!(tag, Tag::Link(..)));
+    let found = pass.scan(&runs, |_, text| {
+        let reference = parse_reference(text)?;
+        Some((reference.len, reference))
+    });
+    for (range, reference) in found {
+        let Some(target) = targets.get(reference.label) else {
+            let message = format!("no element has the label `{}`", reference.label);
+            pass.diagnostic(range, message);
+            continue;
+        };
+        let link_text = if reference.eqref {
+            format!("({})", target.number)
+        } else {
+            format!("{} {}", target.kind.name(), target.number)
+        };
+        let embed = Embed::Reference {
+            label: reference.label.to_string(),
+            text: link_text,
+            position: pass.original_range(range.clone()),
+        };
+        pass.embed_inline(range, embed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::prepare::Prepared;
+    use crate::Props;
+
+    fn prepare(src: &'static str) -> Prepared {
+        Prepared::new(&yew::props!(Props { src }))
+    }
+
+    /// the text and the original position of the references
+    fn references(prepared: &Prepared) -> Vec<(&str, Range<usize>)> {
+        prepared
+            .embeds
+            .iter()
+            .filter_map(|e| match e {
+                Embed::Reference { text, position, .. } => Some((text.as_str(), position.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "math")]
+    #[wasm_bindgen_test]
+    fn equations_are_numbered_and_referenced() {
+        let prepared = prepare("$$a \\label{eq:a}$$\n\n$$b \\label{eq:b}$$\n\nSee [@eq:b] and \\eqref{eq:a}.\n");
+        assert_eq!(
+            prepared.source,
+            "<YewMdEmbed id=\"0\"/>\n\n<YewMdEmbed id=\"1\"/>\n\nSee <YewMdEmbed id=\"2\"/> and <YewMdEmbed id=\"3\"/>.\n"
+        );
+        assert!(prepared.diagnostics.is_empty());
+        assert!(matches!(
+            &prepared.embeds[1],
+            Embed::Equation { label, number: 2, math } if label == "eq:b" && math.position == (20..38)
+        ));
+        assert_eq!(references(&prepared), [("Equation 2", 44..51), ("(1)", 56..68)]);
+        // the source map leads from the prepared source to the references
+        assert_eq!(prepared.original_range(48..68), 44..51);
+    }
+
+    #[cfg(feature = "math")]
+    #[wasm_bindgen_test]
+    fn duplicate_and_unknown_labels_are_reported() {
+        let prepared = prepare("$$a \\label{eq:a}$$\n\n$$b \\label{eq:a}$$\n\n[@eq:c] $$c \\label{c}$$\n");
+        let positions: Vec<_> = prepared.diagnostics.iter().map(|d| d.position.clone()).collect();
+        assert_eq!(positions, [20..38, 48..63, 40..47]);
+        // the formula with a label of another kind is still rendered, without a number
+        assert!(matches!(&prepared.embeds[2], Embed::Math(math) if math.tex == "c "));
+    }
+
+    #[wasm_bindgen_test]
+    fn figures_with_a_caption() {
+        let src = "![A plot](plot.png) {#fig:plot}\nThe caption\n\nSee [@fig:plot].\n";
+        let prepared = Prepared::new(&yew::props!(Props { src, figure_captions: true }));
+        assert_eq!(
+            prepared.source,
+            "\n\n<YewMdEmbed id=\"0\">\n\n<YewMdEmbed id=\"1\">\n\n\nThe caption\n\n</YewMdEmbed>\n\n</YewMdEmbed>\n\n\n\nSee <YewMdEmbed id=\"2\"/>.\n"
+        );
+        assert!(matches!(
+            &prepared.embeds[0],
+            Embed::Figure { caption: None, alt, number: 1, .. } if alt == "A plot"
+        ));
+        assert_eq!(references(&prepared), [("Figure 1", 49..64)]);
+    }
+
+    #[wasm_bindgen_test]
+    fn tables_with_a_caption() {
+        let prepared = prepare("| a |\n|---|\n| 1 |\n\nTable: Values {#tbl:v}\n\nSee [@tbl:v].\n");
+        assert_eq!(
+            prepared.source,
+            "\n\n<YewMdEmbed id=\"0\">\n\n| a |\n|---|\n| 1 |\n\n\n\n<YewMdEmbed id=\"1\">\n\nValues\n\n</YewMdEmbed>\n\n</YewMdEmbed>\n\n\n\nSee <YewMdEmbed id=\"2\"/>.\n"
+        );
+        assert!(matches!(&prepared.embeds[0], Embed::TableFigure { label: Some(label) } if label == "tbl:v"));
+        assert_eq!(references(&prepared), [("Table 1", 47..55)]);
+        // the caption keeps its place in the source
+        let caption = prepared.source.find("Values").unwrap();
+        assert_eq!(prepared.original_range(caption..caption + 6), 26..32);
+    }
+
+    #[wasm_bindgen_test]
+    fn a_paragraph_without_the_prefix_is_not_a_caption() {
+        let src = "| a |\n|---|\n| 1 |\n\nTables: no\n";
+        let prepared = prepare(src);
+        assert_eq!(prepared.source, src);
+        assert!(prepared.embeds.is_empty());
+    }
+}